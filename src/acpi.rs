@@ -0,0 +1,194 @@
+use alloc::vec::Vec;
+use x86_64::PhysAddr;
+
+use crate::memory::phys_to_virt;
+
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+const MADT_SIGNATURE: &[u8; 4] = b"APIC";
+const SDT_HEADER_SIZE: usize = 36;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcpiError {
+    NoRsdp,
+    NoMadt,
+    BadChecksum,
+    Malformed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Processor {
+    pub acpi_id: u8,
+    pub apic_id: u8,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Madt {
+    pub local_apic_address: PhysAddr,
+    pub processors: Vec<Processor>,
+}
+
+pub fn madt() -> Result<Madt, AcpiError> {
+    let rsdp = find_rsdp().ok_or(AcpiError::NoRsdp)?;
+    let rsdp = unsafe { physical_bytes(rsdp, 36) };
+    let revision = rsdp[15];
+
+    // acpi 2.0 and later point at the xsdt, which has 64 bit entries
+    let (root, entry_size) = if revision >= 2 && read_u64(rsdp, 24) != 0 {
+        (PhysAddr::new(read_u64(rsdp, 24)), 8)
+    } else {
+        (PhysAddr::new(read_u32(rsdp, 16) as u64), 4)
+    };
+    let root = unsafe { table(root)? };
+    let entries = &root[SDT_HEADER_SIZE..];
+    for i in 0..entries.len() / entry_size {
+        let address = match entry_size {
+            8 => read_u64(entries, i * 8),
+            _ => read_u32(entries, i * 4) as u64
+        };
+        let header = unsafe { physical_bytes(PhysAddr::new(address), SDT_HEADER_SIZE) };
+        if &header[0..4] == MADT_SIGNATURE {
+            let madt = unsafe { table(PhysAddr::new(address))? };
+            return parse_madt(madt);
+        }
+    }
+    return Err(AcpiError::NoMadt);
+}
+
+pub fn parse_madt(table: &[u8]) -> Result<Madt, AcpiError> {
+    if table.len() < SDT_HEADER_SIZE + 8 || &table[0..4] != MADT_SIGNATURE {
+        return Err(AcpiError::Malformed);
+    }
+    let mut local_apic_address = read_u32(table, SDT_HEADER_SIZE) as u64;
+    let mut processors = Vec::new();
+
+    let mut offset = SDT_HEADER_SIZE + 8;
+    while offset + 2 <= table.len() {
+        let kind = table[offset];
+        let length = table[offset + 1] as usize;
+        if length < 2 || offset + length > table.len() {
+            return Err(AcpiError::Malformed);
+        }
+        let entry = &table[offset..offset + length];
+        match kind {
+            // processor local apic
+            0 if length >= 8 => processors.push(Processor {
+                acpi_id: entry[2],
+                apic_id: entry[3],
+                enabled: read_u32(entry, 4) & 1 != 0
+            }),
+            // local apic address override
+            5 if length >= 12 => local_apic_address = read_u64(entry, 4),
+            _ => {}
+        }
+        offset += length;
+    }
+    return Ok(Madt {
+        local_apic_address: PhysAddr::new(local_apic_address),
+        processors
+    });
+}
+
+// the rsdp sits on a 16 byte boundary in the first kilobyte of the ebda or in the bios area
+fn find_rsdp() -> Option<PhysAddr> {
+    let ebda = unsafe { *phys_to_virt(PhysAddr::new(0x40e)).as_ptr::<u16>() } as u64 * 16;
+    if ebda != 0 {
+        if let Some(rsdp) = scan_rsdp(PhysAddr::new(ebda), 1024) {
+            return Some(rsdp);
+        }
+    }
+    return scan_rsdp(PhysAddr::new(0xe0000), 0x20000);
+}
+
+fn scan_rsdp(start: PhysAddr, size: usize) -> Option<PhysAddr> {
+    let area = unsafe { physical_bytes(start, size) };
+    for offset in (0..size - 20).step_by(16) {
+        if &area[offset..offset + 8] == RSDP_SIGNATURE && checksum(&area[offset..offset + 20]) {
+            return Some(start + offset as u64);
+        }
+    }
+    return None;
+}
+
+// a whole table, length taken from its header
+unsafe fn table(address: PhysAddr) -> Result<&'static [u8], AcpiError> {
+    let header = physical_bytes(address, SDT_HEADER_SIZE);
+    let length = read_u32(header, 4) as usize;
+    if length < SDT_HEADER_SIZE {
+        return Err(AcpiError::Malformed);
+    }
+    let table = physical_bytes(address, length);
+    if !checksum(table) {
+        return Err(AcpiError::BadChecksum);
+    }
+    return Ok(table);
+}
+
+unsafe fn physical_bytes(address: PhysAddr, size: usize) -> &'static [u8] {
+    return core::slice::from_raw_parts(phys_to_virt(address).as_ptr(), size);
+}
+
+// every acpi structure sums to zero
+pub fn checksum(bytes: &[u8]) -> bool {
+    return bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0;
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    return u32::from_le_bytes(value);
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    return u64::from_le_bytes(value);
+}
+
+#[cfg(test)]
+fn test_madt(entries: &[&[u8]]) -> Vec<u8> {
+    let mut table = Vec::new();
+    table.extend_from_slice(MADT_SIGNATURE);
+    table.extend_from_slice(&[0; SDT_HEADER_SIZE - 4]);
+    table.extend_from_slice(&0xfee0_0000u32.to_le_bytes());
+    table.extend_from_slice(&1u32.to_le_bytes());
+    for entry in entries {
+        table.extend_from_slice(entry);
+    }
+    let length = table.len() as u32;
+    table[4..8].copy_from_slice(&length.to_le_bytes());
+    return table;
+}
+
+#[test_case]
+fn test_parse_madt_processors() {
+    let table = test_madt(&[
+        &[0, 8, 0, 0, 1, 0, 0, 0],
+        &[1, 12, 0, 0, 0, 0, 0xc0, 0xfe, 0, 0, 0, 0],
+        &[0, 8, 1, 2, 0, 0, 0, 0]
+    ]);
+    let madt = parse_madt(&table).unwrap();
+    assert_eq!(madt.local_apic_address, PhysAddr::new(0xfee0_0000));
+    assert_eq!(madt.processors.len(), 2);
+    assert_eq!(madt.processors[0], Processor { acpi_id: 0, apic_id: 0, enabled: true });
+    assert_eq!(madt.processors[1], Processor { acpi_id: 1, apic_id: 2, enabled: false });
+}
+
+#[test_case]
+fn test_parse_madt_address_override() {
+    let table = test_madt(&[&[5, 12, 0, 0, 0, 0, 0xd0, 0xfe, 0, 0, 0, 0]]);
+    let madt = parse_madt(&table).unwrap();
+    assert_eq!(madt.local_apic_address, PhysAddr::new(0xfed0_0000));
+}
+
+#[test_case]
+fn test_parse_madt_truncated_entry() {
+    let table = test_madt(&[&[0, 16, 0, 0]]);
+    assert_eq!(parse_madt(&table).unwrap_err(), AcpiError::Malformed);
+}
+
+#[test_case]
+fn test_checksum() {
+    assert!(checksum(&[0x10, 0xf0]));
+    assert!(!checksum(&[0x10, 0xef]));
+}
@@ -0,0 +1,159 @@
+use core::fmt::{self, Arguments, Write};
+use core::ops::BitOr;
+
+use crate::serial::{self, Channel};
+use crate::sync::IrqSpinLock;
+use crate::vga_buffer::{self, ansi, ColorCode};
+
+// where print! output goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sinks(u8);
+
+impl Sinks {
+    pub const NONE: Sinks = Sinks(0);
+    pub const SCREEN: Sinks = Sinks(1);
+    pub const SERIAL: Sinks = Sinks(2);
+
+    pub fn contains(&self, other: Sinks) -> bool {
+        return self.0 & other.0 == other.0;
+    }
+}
+
+impl BitOr for Sinks {
+    type Output = Sinks;
+
+    fn bitor(self, other: Sinks) -> Sinks {
+        return Sinks(self.0 | other.0);
+    }
+}
+
+// rewrites screen output for a serial terminal: vga colours become sgr sequences,
+// cursor movement is dropped and newlines get a carriage return
+struct SerialMirror {
+    parser: ansi::Parser,
+    // None until the first sgr sequence, the screen starts in its default colour
+    color_code: Option<ColorCode>,
+    bold: bool,
+    colors: bool,
+}
+
+struct Multiplexer {
+    sinks: Sinks,
+    mirror: SerialMirror,
+}
+
+static MULTIPLEXER: IrqSpinLock<Multiplexer> = IrqSpinLock::named("console multiplexer", Multiplexer {
+    sinks: Sinks(Sinks::SCREEN.0 | Sinks::SERIAL.0),
+    mirror: SerialMirror::new()
+});
+
+impl SerialMirror {
+
+    const fn new() -> SerialMirror {
+        return SerialMirror {
+            parser: ansi::Parser::new(),
+            color_code: None,
+            bold: false,
+            colors: true
+        };
+    }
+
+    // everything written to `out` is what the terminal should receive
+    fn translate(&mut self, c: char, out: &mut impl Write) -> fmt::Result {
+        return match self.parser.advance(c) {
+            Some(ansi::Action::Print('\n')) => out.write_str("\r\n"),
+            Some(ansi::Action::Print(c)) => out.write_char(c),
+            Some(ansi::Action::SelectGraphicRendition(params)) => {
+                let mut color_code = self.color_code.unwrap_or_default();
+                for param in params.iter() {
+                    color_code = color_code.select_graphic_rendition(param, &mut self.bold);
+                }
+                self.color_code = Some(color_code);
+                match self.colors {
+                    true => color_code.write_sgr(out),
+                    false => Ok(())
+                }
+            },
+            _ => Ok(())
+        };
+    }
+}
+
+struct SerialWriter;
+
+impl Write for SerialWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        serial::_channel_print(Channel::Console, format_args!("{}", s));
+        return Ok(());
+    }
+}
+
+struct MirrorWriter<'a> {
+    mirror: &'a mut SerialMirror,
+}
+
+impl Write for MirrorWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.mirror.translate(c, &mut SerialWriter)?;
+        }
+        return Ok(());
+    }
+}
+
+pub fn sinks() -> Sinks {
+    return MULTIPLEXER.lock().sinks;
+}
+
+pub fn set_sinks(sinks: Sinks) {
+    MULTIPLEXER.lock().sinks = sinks;
+}
+
+// turns colour sequences on the serial mirror on or off
+pub fn set_serial_colors(colors: bool) {
+    MULTIPLEXER.lock().mirror.colors = colors;
+}
+
+pub fn _print(args: Arguments) {
+    let sinks = sinks();
+    if sinks.contains(Sinks::SCREEN) {
+        vga_buffer::_print(args);
+    }
+    if sinks.contains(Sinks::SERIAL) {
+        let mut multiplexer = MULTIPLEXER.lock();
+        MirrorWriter { mirror: &mut multiplexer.mirror }.write_fmt(args).unwrap();
+    }
+}
+
+#[cfg(test)]
+struct Captured {
+    bytes: [u8; 64],
+    len: usize,
+}
+
+#[cfg(test)]
+impl Write for Captured {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.bytes[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len = self.len + s.len();
+        return Ok(());
+    }
+}
+
+#[test_case]
+fn test_serial_mirror_translation() {
+    let mut mirror = SerialMirror::new();
+    let mut out = Captured { bytes: [0; 64], len: 0 };
+    for c in "a\x1b[2J\x1b[1;31mb\x1b[0m\n".chars() {
+        mirror.translate(c, &mut out).unwrap();
+    }
+    assert_eq!(&out.bytes[..out.len], &b"a\x1b[0;91;40mb\x1b[0m\r\n"[..]);
+
+    let mut mirror = SerialMirror::new();
+    mirror.colors = false;
+    let mut out = Captured { bytes: [0; 64], len: 0 };
+    for c in "\x1b[34mc".chars() {
+        mirror.translate(c, &mut out).unwrap();
+    }
+    assert_eq!(&out.bytes[..out.len], &b"c"[..]);
+}
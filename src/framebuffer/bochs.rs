@@ -0,0 +1,70 @@
+use x86_64::instructions::port::Port;
+use x86_64::PhysAddr;
+
+use crate::pci;
+
+const VBE_DISPI_IOPORT_INDEX: u16 = 0x01ce;
+const VBE_DISPI_IOPORT_DATA: u16 = 0x01cf;
+
+const VBE_DISPI_INDEX_ID: u16 = 0;
+const VBE_DISPI_INDEX_XRES: u16 = 1;
+const VBE_DISPI_INDEX_YRES: u16 = 2;
+const VBE_DISPI_INDEX_BPP: u16 = 3;
+const VBE_DISPI_INDEX_ENABLE: u16 = 4;
+const VBE_DISPI_INDEX_VIRT_WIDTH: u16 = 6;
+const VBE_DISPI_INDEX_X_OFFSET: u16 = 8;
+const VBE_DISPI_INDEX_Y_OFFSET: u16 = 9;
+
+const VBE_DISPI_ID0: u16 = 0xb0c0;
+const VBE_DISPI_ID5: u16 = 0xb0c5;
+
+const VBE_DISPI_DISABLED: u16 = 0x00;
+const VBE_DISPI_ENABLED: u16 = 0x01;
+const VBE_DISPI_LFB_ENABLED: u16 = 0x40;
+
+// qemu's standard vga and bochs' vga share this pci id
+const PCI_VENDOR: u16 = 0x1234;
+const PCI_DEVICE: u16 = 0x1111;
+
+fn read(index: u16) -> u16 {
+    let mut index_port: Port<u16> = Port::new(VBE_DISPI_IOPORT_INDEX);
+    let mut data_port: Port<u16> = Port::new(VBE_DISPI_IOPORT_DATA);
+    unsafe {
+        index_port.write(index);
+        return data_port.read();
+    }
+}
+
+fn write(index: u16, value: u16) {
+    let mut index_port: Port<u16> = Port::new(VBE_DISPI_IOPORT_INDEX);
+    let mut data_port: Port<u16> = Port::new(VBE_DISPI_IOPORT_DATA);
+    unsafe {
+        index_port.write(index);
+        data_port.write(value);
+    }
+}
+
+pub fn is_present() -> bool {
+    let id = read(VBE_DISPI_INDEX_ID);
+    return id >= VBE_DISPI_ID0 && id <= VBE_DISPI_ID5;
+}
+
+pub fn set_mode(width: u16, height: u16, bpp: u16) -> bool {
+    write(VBE_DISPI_INDEX_ENABLE, VBE_DISPI_DISABLED);
+    write(VBE_DISPI_INDEX_XRES, width);
+    write(VBE_DISPI_INDEX_YRES, height);
+    write(VBE_DISPI_INDEX_BPP, bpp);
+    write(VBE_DISPI_INDEX_VIRT_WIDTH, width);
+    write(VBE_DISPI_INDEX_X_OFFSET, 0);
+    write(VBE_DISPI_INDEX_Y_OFFSET, 0);
+    write(VBE_DISPI_INDEX_ENABLE, VBE_DISPI_ENABLED | VBE_DISPI_LFB_ENABLED);
+    // the device clamps modes it can't do, so read them back
+    return read(VBE_DISPI_INDEX_XRES) == width
+        && read(VBE_DISPI_INDEX_YRES) == height
+        && read(VBE_DISPI_INDEX_BPP) == bpp;
+}
+
+pub fn framebuffer_address() -> Option<PhysAddr> {
+    let device = pci::find(PCI_VENDOR, PCI_DEVICE)?;
+    return Some(PhysAddr::new((device.bar(0) & 0xffff_fff0) as u64));
+}
@@ -0,0 +1,144 @@
+use super::Framebuffer;
+use super::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::vga_buffer::ansi::{self, Action};
+
+use core::fmt::{Result, Write};
+
+// the vga text palette, so colours look the same in both consoles
+const PALETTE: [u32; 16] = [
+    0x000000, 0x0000aa, 0x00aa00, 0x00aaaa, 0xaa0000, 0xaa00aa, 0xaa5500, 0xaaaaaa,
+    0x555555, 0x5555ff, 0x55ff55, 0x55ffff, 0xff5555, 0xff55ff, 0xffff55, 0xffffff,
+];
+
+// sgr colour number to palette index
+const ANSI_COLORS: [usize; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+const DEFAULT_FOREGROUND: usize = 2;
+const DEFAULT_BACKGROUND: usize = 0;
+
+pub struct FramebufferConsole {
+    framebuffer: Framebuffer,
+    columns: usize,
+    rows: usize,
+    column: usize,
+    row: usize,
+    foreground: usize,
+    background: usize,
+    ansi: ansi::Parser,
+}
+
+impl FramebufferConsole {
+
+    pub fn new(framebuffer: Framebuffer) -> FramebufferConsole {
+        let mut console = FramebufferConsole {
+            columns: framebuffer.width() / GLYPH_WIDTH,
+            rows: framebuffer.height() / GLYPH_HEIGHT,
+            framebuffer,
+            column: 0,
+            row: 0,
+            foreground: DEFAULT_FOREGROUND,
+            background: DEFAULT_BACKGROUND,
+            ansi: ansi::Parser::new()
+        };
+        console.clear();
+        return console;
+    }
+
+    pub fn columns(&self) -> usize {
+        return self.columns;
+    }
+
+    pub fn rows(&self) -> usize {
+        return self.rows;
+    }
+
+    pub fn framebuffer(&mut self) -> &mut Framebuffer {
+        return &mut self.framebuffer;
+    }
+
+    pub fn clear(&mut self) {
+        let (width, height) = (self.framebuffer.width(), self.framebuffer.height());
+        self.framebuffer.fill_rect(0, 0, width, height, PALETTE[self.background]);
+        self.column = 0;
+        self.row = 0;
+    }
+
+    fn new_line(&mut self) {
+        self.column = 0;
+        if self.row + 1 < self.rows {
+            self.row = self.row + 1;
+        }
+        else {
+            self.framebuffer.scroll_up(GLYPH_HEIGHT, PALETTE[self.background]);
+        }
+    }
+
+    fn draw_glyph(&mut self, c: char) {
+        let glyph = font::glyph(c);
+        let x = self.column * GLYPH_WIDTH;
+        let y = self.row * GLYPH_HEIGHT;
+        for (dy, line) in glyph.iter().enumerate() {
+            for dx in 0..GLYPH_WIDTH {
+                let color = match line & (0x80 >> dx) {
+                    0 => PALETTE[self.background],
+                    _ => PALETTE[self.foreground]
+                };
+                self.framebuffer.put_pixel(x + dx, y + dy, color);
+            }
+        }
+    }
+
+    fn put_char(&mut self, c: char) {
+        match c {
+            '\n' => self.new_line(),
+            c => {
+                if self.column == self.columns {
+                    self.new_line();
+                }
+                self.draw_glyph(c);
+                self.column = self.column + 1;
+            }
+        }
+    }
+
+    pub fn write_char(&mut self, c: char) {
+        match self.ansi.advance(c) {
+            Some(Action::Print(c)) => self.put_char(c),
+            Some(Action::SelectGraphicRendition(params)) => {
+                for param in params.iter() {
+                    match param {
+                        0 => {
+                            self.foreground = DEFAULT_FOREGROUND;
+                            self.background = DEFAULT_BACKGROUND;
+                        },
+                        1 => self.foreground = self.foreground | 0x08,
+                        22 => self.foreground = self.foreground & 0x07,
+                        30..=37 => self.foreground = ANSI_COLORS[(param - 30) as usize],
+                        39 => self.foreground = DEFAULT_FOREGROUND,
+                        40..=47 => self.background = ANSI_COLORS[(param - 40) as usize],
+                        49 => self.background = DEFAULT_BACKGROUND,
+                        90..=97 => self.foreground = ANSI_COLORS[(param - 90) as usize] | 0x08,
+                        100..=107 => self.background = ANSI_COLORS[(param - 100) as usize] | 0x08,
+                        _ => {}
+                    }
+                }
+            },
+            Some(Action::CursorPosition(row, col)) => {
+                self.row = (row as usize - 1).min(self.rows - 1);
+                self.column = (col as usize - 1).min(self.columns - 1);
+            },
+            Some(Action::EraseInDisplay(2)) | Some(Action::EraseInDisplay(3)) => self.clear(),
+            // the remaining sequences only matter for the text console
+            Some(_) | None => {}
+        }
+    }
+}
+
+impl Write for FramebufferConsole {
+    fn write_str(&mut self, s: &str) -> Result {
+        for c in s.chars() {
+            FramebufferConsole::write_char(self, c);
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,142 @@
+use core::fmt::{Arguments, Write};
+
+use spin::Mutex;
+use x86_64::VirtAddr;
+use x86_64::structures::paging::PageTableFlags;
+
+use crate::graphics::Surface;
+
+pub mod bochs;
+pub mod console;
+pub mod font;
+use console::FramebufferConsole;
+
+pub const FRAMEBUFFER_START: usize = 0x_5555_0000_0000;
+
+pub static CONSOLE: Mutex<Option<FramebufferConsole>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramebufferError {
+    NotPresent,
+    UnsupportedMode,
+    MappingFailed,
+}
+
+// 32 bits per pixel, 0x00rrggbb
+pub struct Framebuffer {
+    pixels: &'static mut [u32],
+    width: usize,
+    height: usize,
+}
+
+impl Framebuffer {
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.height;
+    }
+
+    pub fn put_pixel(&mut self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> u32 {
+        return self.pixels[y * self.width + x];
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        let x_end = (x + width).min(self.width);
+        let y_end = (y + height).min(self.height);
+        for row in y.min(y_end)..y_end {
+            let start = row * self.width;
+            for pixel in &mut self.pixels[start + x.min(x_end)..start + x_end] {
+                *pixel = color;
+            }
+        }
+    }
+
+    // moves everything up by `lines` pixel rows and clears the bottom
+    pub fn scroll_up(&mut self, lines: usize, color: u32) {
+        let lines = lines.min(self.height);
+        let width = self.width;
+        self.pixels.copy_within(lines * width.., 0);
+        let height = self.height;
+        self.fill_rect(0, height - lines, width, lines, color);
+    }
+}
+
+impl Surface for Framebuffer {
+    fn width(&self) -> usize {
+        return self.width;
+    }
+
+    fn height(&self) -> usize {
+        return self.height;
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> u32 {
+        return self.pixels[y * self.width + x];
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    fn write_span(&mut self, x: usize, y: usize, pixels: &[u32]) {
+        let start = y * self.width + x;
+        self.pixels[start..start + pixels.len()].copy_from_slice(pixels);
+    }
+}
+
+pub fn init(width: u16, height: u16) -> Result<(), FramebufferError> {
+    if !bochs::is_present() {
+        return Err(FramebufferError::NotPresent);
+    }
+    let physical_start = bochs::framebuffer_address().ok_or(FramebufferError::NotPresent)?;
+    if !bochs::set_mode(width, height, 32) {
+        return Err(FramebufferError::UnsupportedMode);
+    }
+
+    let size = width as usize * height as usize;
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::WRITE_THROUGH;
+    crate::memory::map_physical_region(
+        physical_start,
+        (size * 4) as u64,
+        VirtAddr::new(FRAMEBUFFER_START as u64),
+        flags
+    ).map_err(|_| FramebufferError::MappingFailed)?;
+
+    let framebuffer = Framebuffer {
+        pixels: unsafe { core::slice::from_raw_parts_mut(FRAMEBUFFER_START as *mut u32, size) },
+        width: width as usize,
+        height: height as usize
+    };
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        *CONSOLE.lock() = Some(FramebufferConsole::new(framebuffer));
+    });
+    return Ok(());
+}
+
+pub fn is_active() -> bool {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        return CONSOLE.lock().is_some();
+    });
+}
+
+// returns false when no graphics mode has been set
+pub fn _print(args: Arguments) -> bool {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        return match CONSOLE.lock().as_mut() {
+            Some(console) => {
+                console.write_fmt(args).unwrap();
+                true
+            },
+            None => false
+        };
+    });
+}
@@ -0,0 +1,446 @@
+// a gdb remote serial protocol stub on the debug serial channel, entered from
+// the int3 and #DB traps, e.g. `target remote /tmp/gdb` with qemu's
+// `-serial pipe:/tmp/gdb` as the second serial port
+
+use spin::Mutex;
+use x86_64::VirtAddr;
+use x86_64::registers::control::{Cr0, Cr0Flags};
+use x86_64::structures::paging::MapperAllSizes;
+
+use crate::interrupts::trap::{TrapFrame, BREAKPOINT_VECTOR, DEBUG_VECTOR, TRAP_FLAG};
+use crate::serial::{self, Channel, SerialError};
+
+pub mod packet;
+use packet::{Event, PacketReader, Response};
+
+const MAX_BREAKPOINTS: usize = 32;
+const INT3: u8 = 0xcc;
+
+// gdb's amd64 register numbering, 16 general purpose registers, rip and eflags, then segments
+const NUM_REGISTERS: usize = 24;
+const RIP: usize = 16;
+
+#[derive(Clone, Copy)]
+struct Breakpoint {
+    address: u64,
+    original: u8,
+}
+
+struct Stub {
+    enabled: bool,
+    breakpoints: [Option<Breakpoint>; MAX_BREAKPOINTS],
+    // a breakpoint lifted for one instruction so execution can move past it
+    stepping_over: Option<Breakpoint>,
+    single_step: bool,
+}
+
+static STUB: Mutex<Stub> = Mutex::new(Stub {
+    enabled: false,
+    breakpoints: [None; MAX_BREAKPOINTS],
+    stepping_over: None,
+    single_step: false
+});
+
+fn register_slot(frame: &mut TrapFrame, register: usize) -> Option<&mut u64> {
+    return match register {
+        0 => Some(&mut frame.rax),
+        1 => Some(&mut frame.rbx),
+        2 => Some(&mut frame.rcx),
+        3 => Some(&mut frame.rdx),
+        4 => Some(&mut frame.rsi),
+        5 => Some(&mut frame.rdi),
+        6 => Some(&mut frame.rbp),
+        7 => Some(&mut frame.rsp),
+        8 => Some(&mut frame.r8),
+        9 => Some(&mut frame.r9),
+        10 => Some(&mut frame.r10),
+        11 => Some(&mut frame.r11),
+        12 => Some(&mut frame.r12),
+        13 => Some(&mut frame.r13),
+        14 => Some(&mut frame.r14),
+        15 => Some(&mut frame.r15),
+        RIP => Some(&mut frame.rip),
+        17 => Some(&mut frame.rflags),
+        // writes to cs and ss are dropped, a stray selector would fault the iretq in the stub
+        _ => None
+    };
+}
+
+// data segment registers are not saved and read as zero
+fn register(frame: &TrapFrame, register: usize) -> u64 {
+    let mut frame = *frame;
+    return match register {
+        18 => frame.cs,
+        19 => frame.ss,
+        _ => register_slot(&mut frame, register).map_or(0, |value| *value)
+    };
+}
+
+fn register_size(register: usize) -> usize {
+    return if register <= RIP { 8 } else { 4 };
+}
+
+// only touch memory that is mapped, a fault inside the stub would be fatal
+fn accessible(address: u64, len: u64) -> bool {
+    let mapper = match crate::memory::MAPPER.try_lock() {
+        Some(mapper) => mapper,
+        None => return false
+    };
+    let mapper = match mapper.as_ref() {
+        Some(mapper) => mapper,
+        None => return false
+    };
+    let end = match address.checked_add(len) {
+        Some(end) => end,
+        None => return false
+    };
+    let mut page = address & !0xfff;
+    while page < end {
+        let mapped = VirtAddr::try_new(page).ok().and_then(|page| mapper.translate_addr(page)).is_some();
+        if !mapped {
+            return false;
+        }
+        page = page + 0x1000;
+    }
+    return true;
+}
+
+// kernel code is mapped read-only, so writes go around the write protection
+fn write_byte(address: u64, byte: u8) {
+    let flags = Cr0::read();
+    unsafe {
+        Cr0::write(flags - Cr0Flags::WRITE_PROTECT);
+        core::ptr::write_volatile(address as *mut u8, byte);
+        Cr0::write(flags);
+    }
+}
+
+fn read_byte(address: u64) -> u8 {
+    return unsafe { core::ptr::read_volatile(address as *const u8) };
+}
+
+fn send_packet(data: &[u8]) {
+    let sum = packet::checksum(data);
+    serial::write_bytes(Channel::Debug, b"$");
+    serial::write_bytes(Channel::Debug, data);
+    serial::write_bytes(Channel::Debug, &[b'#', packet::hex_digit(sum >> 4), packet::hex_digit(sum)]);
+}
+
+fn split(data: &[u8], separator: u8) -> Option<(&[u8], &[u8])> {
+    let index = data.iter().position(|byte| *byte == separator)?;
+    return Some((&data[..index], &data[index + 1..]));
+}
+
+impl Stub {
+
+    fn breakpoint_index(&self, address: u64) -> Option<usize> {
+        return self.breakpoints.iter()
+            .position(|breakpoint| breakpoint.map_or(false, |breakpoint| breakpoint.address == address));
+    }
+
+    fn insert_breakpoint(&mut self, address: u64) -> bool {
+        if self.breakpoint_index(address).is_some() {
+            return true;
+        }
+        let slot = match self.breakpoints.iter().position(|breakpoint| breakpoint.is_none()) {
+            Some(slot) => slot,
+            None => return false
+        };
+        if !accessible(address, 1) {
+            return false;
+        }
+        self.breakpoints[slot] = Some(Breakpoint { address, original: read_byte(address) });
+        write_byte(address, INT3);
+        return true;
+    }
+
+    fn remove_breakpoint(&mut self, address: u64) -> bool {
+        let index = match self.breakpoint_index(address) {
+            Some(index) => index,
+            None => return false
+        };
+        let breakpoint = self.breakpoints[index].take().unwrap();
+        write_byte(breakpoint.address, breakpoint.original);
+        return true;
+    }
+
+    fn remove_all_breakpoints(&mut self) {
+        for index in 0..MAX_BREAKPOINTS {
+            if let Some(breakpoint) = self.breakpoints[index].take() {
+                write_byte(breakpoint.address, breakpoint.original);
+            }
+        }
+    }
+
+    fn resume(&mut self, frame: &mut TrapFrame, step: bool) {
+        self.single_step = step;
+        if let Some(index) = self.breakpoint_index(frame.rip) {
+            // run the original instruction with the trap flag, then put the int3 back
+            let breakpoint = self.breakpoints[index].take().unwrap();
+            write_byte(breakpoint.address, breakpoint.original);
+            self.stepping_over = Some(breakpoint);
+            frame.rflags = frame.rflags | TRAP_FLAG;
+        }
+        else if step {
+            frame.rflags = frame.rflags | TRAP_FLAG;
+        }
+    }
+
+    // answers one packet, returns true when execution should continue
+    fn command(&mut self, packet: &[u8], frame: &mut TrapFrame, response: &mut Response) -> bool {
+        let (command, arguments) = match packet.split_first() {
+            Some((command, arguments)) => (*command, arguments),
+            None => return false
+        };
+        match command {
+            b'?' => response.push_str("S05"),
+            b'g' => {
+                for n in 0..NUM_REGISTERS {
+                    response.push_hex_le(register(frame, n), register_size(n));
+                }
+            },
+            b'G' => {
+                let mut rest = arguments;
+                for n in 0..NUM_REGISTERS {
+                    let size = register_size(n) * 2;
+                    if rest.len() < size {
+                        break;
+                    }
+                    if let (Some(value), Some(slot)) = (packet::parse_hex_le(&rest[..size]), register_slot(frame, n)) {
+                        *slot = value;
+                    }
+                    rest = &rest[size..];
+                }
+                response.push_str("OK");
+            },
+            b'p' => match packet::parse_hex(arguments) {
+                Some(n) if (n as usize) < NUM_REGISTERS => {
+                    response.push_hex_le(register(frame, n as usize), register_size(n as usize));
+                },
+                _ => response.push_str("E01")
+            },
+            b'P' => {
+                let write = split(arguments, b'=')
+                    .and_then(|(n, value)| Some((packet::parse_hex(n)? as usize, packet::parse_hex_le(value)?)));
+                match write {
+                    Some((n, value)) if n < NUM_REGISTERS => {
+                        if let Some(slot) = register_slot(frame, n) {
+                            *slot = value;
+                        }
+                        response.push_str("OK");
+                    },
+                    _ => response.push_str("E01")
+                }
+            },
+            b'm' => {
+                let range = split(arguments, b',')
+                    .and_then(|(address, len)| Some((packet::parse_hex(address)?, packet::parse_hex(len)?)));
+                match range {
+                    Some((address, len)) if len <= (packet::PACKET_SIZE / 2) as u64 && accessible(address, len) => {
+                        for offset in 0..len {
+                            response.push_hex(read_byte(address + offset));
+                        }
+                    },
+                    _ => response.push_str("E14")
+                }
+            },
+            b'M' => {
+                let write = split(arguments, b':').and_then(|(range, data)| {
+                    let (address, len) = split(range, b',')?;
+                    return Some((packet::parse_hex(address)?, packet::parse_hex(len)?, data));
+                });
+                match write {
+                    Some((address, len, data)) if len.checked_mul(2) == Some(data.len() as u64) && accessible(address, len) => {
+                        for (offset, pair) in data.chunks(2).enumerate() {
+                            let byte = packet::parse_hex_le(pair).unwrap_or(0) as u8;
+                            write_byte(address + offset as u64, byte);
+                        }
+                        response.push_str("OK");
+                    },
+                    _ => response.push_str("E14")
+                }
+            },
+            b'Z' | b'z' => {
+                // only software breakpoints, "Z0,address,kind"
+                let mut fields = arguments.split(|byte| *byte == b',');
+                let kind = fields.next();
+                let address = fields.next().and_then(packet::parse_hex);
+                match (kind, address) {
+                    (Some(b"0"), Some(address)) => {
+                        let done = match command {
+                            b'Z' => self.insert_breakpoint(address),
+                            _ => self.remove_breakpoint(address)
+                        };
+                        response.push_str(if done { "OK" } else { "E01" });
+                    },
+                    _ => {}
+                }
+            },
+            b'c' | b's' => {
+                if let Some(address) = packet::parse_hex(arguments) {
+                    frame.rip = address;
+                }
+                self.resume(frame, command == b's');
+                return true;
+            },
+            b'D' | b'k' => {
+                self.remove_all_breakpoints();
+                self.enabled = command == b'D';
+                if command == b'D' {
+                    response.push_str("OK");
+                }
+                self.resume(frame, false);
+                return true;
+            },
+            b'H' => response.push_str("OK"),
+            b'q' => {
+                if arguments.starts_with(b"Supported") {
+                    response.push_str("PacketSize=400");
+                }
+                else if arguments.starts_with(b"Attached") {
+                    response.push_str("1");
+                }
+            },
+            // an empty reply tells gdb the packet is not supported
+            _ => {}
+        }
+        return false;
+    }
+
+    // talks to gdb until it lets the kernel run again
+    fn serve(&mut self, frame: &mut TrapFrame) {
+        let mut reader = PacketReader::new();
+        let mut last = Response::new();
+        last.push_str("S05");
+        send_packet(last.as_bytes());
+        loop {
+            let byte = match serial::poll_read_byte(Channel::Debug) {
+                Some(byte) => byte,
+                None => {
+                    core::sync::atomic::spin_loop_hint();
+                    continue;
+                }
+            };
+            match reader.push(byte) {
+                Some(Event::Packet) => {
+                    serial::write_bytes(Channel::Debug, b"+");
+                    let mut response = Response::new();
+                    let resume = self.command(reader.packet(), frame, &mut response);
+                    if resume && response.is_empty() {
+                        return;
+                    }
+                    send_packet(response.as_bytes());
+                    if resume {
+                        return;
+                    }
+                    last = response;
+                },
+                Some(Event::BadChecksum) => serial::write_bytes(Channel::Debug, b"-"),
+                Some(Event::Nack) => send_packet(last.as_bytes()),
+                Some(Event::Interrupt) => send_packet(b"S05"),
+                Some(Event::Ack) | None => {}
+            }
+        }
+    }
+}
+
+pub fn init() -> Result<(), SerialError> {
+    let com = serial::port_for(Channel::Debug).ok_or(SerialError::NotPresent)?;
+    if !serial::is_present(com) {
+        return Err(SerialError::NotPresent);
+    }
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        STUB.lock().enabled = true;
+    });
+    return Ok(());
+}
+
+// stops in the debugger, e.g. to wait for gdb to attach
+pub fn breakpoint() {
+    x86_64::instructions::interrupts::int3();
+}
+
+// called from the int3 and #DB handlers, returns false when the trap is not for the debugger
+pub fn handle_trap(frame: &mut TrapFrame) -> bool {
+    let mut stub = match STUB.try_lock() {
+        Some(stub) => stub,
+        None => return false
+    };
+    if !stub.enabled {
+        return false;
+    }
+    match frame.vector {
+        BREAKPOINT_VECTOR => {
+            // int3 has already run, report the address of the breakpoint itself
+            if stub.breakpoint_index(frame.rip.wrapping_sub(1)).is_some() {
+                frame.rip = frame.rip - 1;
+            }
+        },
+        DEBUG_VECTOR => {
+            frame.rflags = frame.rflags & !TRAP_FLAG;
+            if let Some(breakpoint) = stub.stepping_over.take() {
+                if let Some(slot) = stub.breakpoints.iter_mut().find(|slot| slot.is_none()) {
+                    *slot = Some(breakpoint);
+                    write_byte(breakpoint.address, INT3);
+                }
+                if !stub.single_step {
+                    return true;
+                }
+            }
+        },
+        _ => {}
+    }
+    stub.single_step = false;
+    stub.serve(frame);
+    return true;
+}
+
+#[test_case]
+fn test_register_commands() {
+    let mut stub = Stub {
+        enabled: true,
+        breakpoints: [None; MAX_BREAKPOINTS],
+        stepping_over: None,
+        single_step: false
+    };
+    let mut frame = TrapFrame {
+        rax: 0x1122_3344_5566_7788,
+        rbx: 0, rcx: 0, rdx: 0, rsi: 0, rdi: 0, rbp: 0,
+        r8: 0, r9: 0, r10: 0, r11: 0, r12: 0, r13: 0, r14: 0, r15: 0,
+        vector: BREAKPOINT_VECTOR,
+        error_code: 0,
+        rip: 0x1000,
+        cs: 8,
+        rflags: 0x202,
+        rsp: 0x2000,
+        ss: 0
+    };
+
+    let mut response = Response::new();
+    stub.command(b"g", &mut frame, &mut response);
+    assert_eq!(response.as_bytes().len(), (17 * 8 + 7 * 4) * 2);
+    assert_eq!(&response.as_bytes()[..16], b"8877665544332211");
+
+    let mut response = Response::new();
+    stub.command(b"P10=0020000000000000", &mut frame, &mut response);
+    assert_eq!(response.as_bytes(), b"OK");
+    assert_eq!(frame.rip, 0x2000);
+
+    // segment registers read back but ignore writes
+    let mut response = Response::new();
+    stub.command(b"P12=2b000000", &mut frame, &mut response);
+    assert_eq!(response.as_bytes(), b"OK");
+    assert_eq!(frame.cs, 8);
+
+    let mut response = Response::new();
+    stub.command(b"m0,ffffffffffffffff", &mut frame, &mut response);
+    assert_eq!(response.as_bytes(), b"E14");
+    let mut response = Response::new();
+    stub.command(b"M0,8000000000000000:00", &mut frame, &mut response);
+    assert_eq!(response.as_bytes(), b"E14");
+
+    let mut response = Response::new();
+    assert!(stub.command(b"s", &mut frame, &mut response));
+    assert!(frame.rflags & TRAP_FLAG != 0);
+    assert!(stub.single_step);
+}
@@ -0,0 +1,251 @@
+use core::fmt;
+
+pub const PACKET_SIZE: usize = 1024;
+
+const INTERRUPT: u8 = 0x03;
+const ESCAPE: u8 = b'}';
+
+pub fn checksum(data: &[u8]) -> u8 {
+    return data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+}
+
+pub fn hex_digit(value: u8) -> u8 {
+    return b"0123456789abcdef"[(value & 0x0f) as usize];
+}
+
+pub fn from_hex_digit(c: u8) -> Option<u8> {
+    return match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None
+    };
+}
+
+// a big endian number as used for addresses and lengths
+pub fn parse_hex(s: &[u8]) -> Option<u64> {
+    if s.is_empty() || s.len() > 16 {
+        return None;
+    }
+    let mut value = 0;
+    for c in s {
+        value = value << 4 | from_hex_digit(*c)? as u64;
+    }
+    return Some(value);
+}
+
+// a value sent in target byte order, like register contents
+pub fn parse_hex_le(s: &[u8]) -> Option<u64> {
+    if s.is_empty() || s.len() % 2 != 0 || s.len() > 16 {
+        return None;
+    }
+    let mut value = 0;
+    for (i, pair) in s.chunks(2).enumerate() {
+        let byte = from_hex_digit(pair[0])? << 4 | from_hex_digit(pair[1])?;
+        value = value | (byte as u64) << (i * 8);
+    }
+    return Some(value);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Packet,
+    BadChecksum,
+    Interrupt,
+    Ack,
+    Nack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    Data,
+    Escaped,
+    Checksum(u8),
+}
+
+// reassembles "$data#xx" packets from single bytes
+pub struct PacketReader {
+    buffer: [u8; PACKET_SIZE],
+    len: usize,
+    state: State,
+    sum: u8,
+    received: u8,
+}
+
+impl PacketReader {
+
+    pub const fn new() -> PacketReader {
+        return PacketReader {
+            buffer: [0; PACKET_SIZE],
+            len: 0,
+            state: State::Idle,
+            sum: 0,
+            received: 0
+        };
+    }
+
+    pub fn packet(&self) -> &[u8] {
+        return &self.buffer[..self.len];
+    }
+
+    pub fn push(&mut self, byte: u8) -> Option<Event> {
+        match self.state {
+            State::Idle => {
+                return match byte {
+                    b'$' => {
+                        self.state = State::Data;
+                        self.len = 0;
+                        self.sum = 0;
+                        None
+                    },
+                    b'+' => Some(Event::Ack),
+                    b'-' => Some(Event::Nack),
+                    INTERRUPT => Some(Event::Interrupt),
+                    _ => None
+                };
+            },
+            State::Data | State::Escaped => {
+                if byte == b'#' && self.state == State::Data {
+                    self.state = State::Checksum(0);
+                    self.received = 0;
+                    return None;
+                }
+                // the checksum covers the bytes as sent, escapes included
+                self.sum = self.sum.wrapping_add(byte);
+                let value = match self.state {
+                    State::Escaped => byte ^ 0x20,
+                    _ if byte == ESCAPE => {
+                        self.state = State::Escaped;
+                        return None;
+                    },
+                    _ => byte
+                };
+                self.state = State::Data;
+                if self.len < PACKET_SIZE {
+                    self.buffer[self.len] = value;
+                    self.len = self.len + 1;
+                }
+                return None;
+            },
+            State::Checksum(digits) => {
+                let digit = match from_hex_digit(byte) {
+                    Some(digit) => digit,
+                    None => {
+                        self.state = State::Idle;
+                        return Some(Event::BadChecksum);
+                    }
+                };
+                self.received = self.received << 4 | digit;
+                if digits == 0 {
+                    self.state = State::Checksum(1);
+                    return None;
+                }
+                self.state = State::Idle;
+                if self.received != self.sum || self.len == PACKET_SIZE {
+                    return Some(Event::BadChecksum);
+                }
+                return Some(Event::Packet);
+            }
+        }
+    }
+}
+
+// the payload of a reply, framed when it is sent
+pub struct Response {
+    bytes: [u8; PACKET_SIZE],
+    len: usize,
+}
+
+impl Response {
+
+    pub const fn new() -> Response {
+        return Response {
+            bytes: [0; PACKET_SIZE],
+            len: 0
+        };
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.bytes[..self.len];
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    pub fn push(&mut self, byte: u8) {
+        if self.len < PACKET_SIZE {
+            self.bytes[self.len] = byte;
+            self.len = self.len + 1;
+        }
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        for byte in s.bytes() {
+            self.push(byte);
+        }
+    }
+
+    pub fn push_hex(&mut self, byte: u8) {
+        self.push(hex_digit(byte >> 4));
+        self.push(hex_digit(byte));
+    }
+
+    // `size` bytes of `value` in target byte order
+    pub fn push_hex_le(&mut self, value: u64, size: usize) {
+        for i in 0..size {
+            self.push_hex((value >> (i * 8)) as u8);
+        }
+    }
+}
+
+impl fmt::Write for Response {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        return Ok(());
+    }
+}
+
+#[test_case]
+fn test_hex_parsing() {
+    assert_eq!(parse_hex(b"ffff8000"), Some(0xffff_8000));
+    assert_eq!(parse_hex(b""), None);
+    assert_eq!(parse_hex(b"1g"), None);
+    assert_eq!(parse_hex_le(b"3412"), Some(0x1234));
+    assert_eq!(parse_hex_le(b"123"), None);
+}
+
+#[test_case]
+fn test_packet_reader() {
+    let mut reader = PacketReader::new();
+    assert_eq!(reader.push(b'+'), Some(Event::Ack));
+    let mut event = None;
+    for byte in b"$m1000,4#8e" {
+        event = reader.push(*byte);
+    }
+    assert_eq!(event, Some(Event::Packet));
+    assert_eq!(reader.packet(), b"m1000,4");
+
+    for byte in b"$m1000,4#00" {
+        event = reader.push(*byte);
+    }
+    assert_eq!(event, Some(Event::BadChecksum));
+    assert_eq!(reader.push(0x03), Some(Event::Interrupt));
+}
+
+#[test_case]
+fn test_packet_reader_escapes() {
+    let mut reader = PacketReader::new();
+    let data = [b'X', b'}', b'#' ^ 0x20];
+    let mut event = None;
+    reader.push(b'$');
+    for byte in data.iter() {
+        reader.push(*byte);
+    }
+    for byte in [b'#', hex_digit(checksum(&data) >> 4), hex_digit(checksum(&data))].iter() {
+        event = reader.push(*byte);
+    }
+    assert_eq!(event, Some(Event::Packet));
+    assert_eq!(reader.packet(), b"X#");
+}
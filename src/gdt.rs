@@ -1,9 +1,14 @@
-use x86_64::VirtAddr;
+use x86_64::{PrivilegeLevel, VirtAddr};
 use x86_64::structures::tss::TaskStateSegment;
-use x86_64::structures::gdt::{GlobalDescriptorTable, Descriptor, SegmentSelector};
+use x86_64::structures::gdt::{GlobalDescriptorTable, Descriptor, DescriptorFlags, SegmentSelector};
 
+use alloc::boxed::Box;
+use alloc::vec;
+use core::cell::Cell;
 use lazy_static::lazy_static;
 
+use crate::cpu_local;
+
 pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
 
 lazy_static! {
@@ -16,31 +21,102 @@ lazy_static! {
             let stack_start = VirtAddr::from_ptr(unsafe { &STACK });
             stack_start + STACK_SIZE
         };
+        // the stack the cpu switches to when an interrupt arrives in ring 3
+        tss.privilege_stack_table[0] = {
+            const STACK_SIZE: usize = 4096 * 5;
+            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+
+            let stack_start = VirtAddr::from_ptr(unsafe { &STACK });
+            stack_start + STACK_SIZE
+        };
         return tss;
     };
 }
 
 lazy_static! {
-    static ref GDT: (GlobalDescriptorTable, Selectors) = {
-        let mut gdt = GlobalDescriptorTable::new();
-        let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
-        let tss_selector = gdt.add_entry(Descriptor::tss_segment(&TSS));
-        return (gdt, Selectors { code_selector, tss_selector })
-    };
+    static ref GDT: (GlobalDescriptorTable, Selectors) = new_gdt(&TSS);
 }
 
-struct Selectors {
-    code_selector: SegmentSelector,
-    tss_selector: SegmentSelector,
+fn new_gdt(tss: &'static TaskStateSegment) -> (GlobalDescriptorTable, Selectors) {
+    // x86_64 has no constructor for a ring 0 data segment
+    let kernel_data = DescriptorFlags::USER_SEGMENT | DescriptorFlags::PRESENT | DescriptorFlags::WRITABLE;
+
+    // syscall and sysret derive the selectors from fixed offsets, which dictates this order
+    let mut gdt = GlobalDescriptorTable::new();
+    let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
+    let data_selector = gdt.add_entry(Descriptor::UserSegment(kernel_data.bits()));
+    let user_data_selector = gdt.add_entry(Descriptor::user_data_segment());
+    let user_code_selector = gdt.add_entry(Descriptor::user_code_segment());
+    let tss_selector = gdt.add_entry(Descriptor::tss_segment(tss));
+    return (gdt, Selectors {
+        code_selector,
+        data_selector,
+        user_code_selector: SegmentSelector::new(user_code_selector.index(), PrivilegeLevel::Ring3),
+        user_data_selector: SegmentSelector::new(user_data_selector.index(), PrivilegeLevel::Ring3),
+        tss_selector
+    });
+}
+
+pub struct Selectors {
+    pub code_selector: SegmentSelector,
+    pub data_selector: SegmentSelector,
+    pub user_code_selector: SegmentSelector,
+    pub user_data_selector: SegmentSelector,
+    pub tss_selector: SegmentSelector,
 }
 
+cpu_local! {
+    // the tss loaded on this cpu, with its interrupt and privilege stacks
+    static CURRENT_TSS: Cell<Option<&'static TaskStateSegment>> = Cell::new(None);
+}
+
+pub fn tss() -> &'static TaskStateSegment {
+    return CURRENT_TSS.get().get().expect("gdt not loaded on this cpu");
+}
+
+// the same on every cpu, only the tss descriptor differs
+pub fn selectors() -> &'static Selectors {
+    return &GDT.1;
+}
+
+// the bootstrap processor's tables are static, they are needed before the heap
 pub fn init() {
-    use x86_64::instructions::segmentation::set_cs;
+    crate::smp::percpu::init_bsp();
+    load(&GDT.0, &GDT.1);
+    CURRENT_TSS.get().set(Some(&*TSS));
+}
+
+// application processors get their own tss, a busy tss can't be loaded twice
+pub fn init_ap() {
+    let mut tss = TaskStateSegment::new();
+    tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = leak_stack(4096);
+    tss.privilege_stack_table[0] = leak_stack(4096 * 5);
+    let tss: &'static TaskStateSegment = Box::leak(Box::new(tss));
+    let gdt: &'static (GlobalDescriptorTable, Selectors) = Box::leak(Box::new(new_gdt(tss)));
+    load(&gdt.0, &gdt.1);
+    CURRENT_TSS.get().set(Some(tss));
+}
+
+fn leak_stack(size: usize) -> VirtAddr {
+    let stack = Box::leak(vec![0u8; size].into_boxed_slice());
+    return VirtAddr::from_ptr(stack.as_ptr()) + size;
+}
+
+fn load(gdt: &'static GlobalDescriptorTable, selectors: &Selectors) {
+    use x86_64::instructions::segmentation::{load_ds, load_es, load_ss, set_cs};
     use x86_64::instructions::tables::load_tss;
 
-    GDT.0.load();
+    gdt.load();
     unsafe {
-        set_cs(GDT.1.code_selector);
-        load_tss(GDT.1.tss_selector);
+        set_cs(selectors.code_selector);
+        load_ss(selectors.data_selector);
+        load_ds(selectors.data_selector);
+        load_es(selectors.data_selector);
+        load_tss(selectors.tss_selector);
     }
-}
\ No newline at end of file
+}
+
+#[test_case]
+fn test_tss_of_bootstrap_processor() {
+    assert!(core::ptr::eq(tss(), &*TSS));
+}
@@ -0,0 +1,176 @@
+use super::{blend, Rect, Surface};
+
+pub struct Bitmap<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: &'a [u32],
+}
+
+// draws onto a surface, clipping everything to `clip` and recording the damaged area
+pub struct Canvas<'a, S: Surface> {
+    surface: &'a mut S,
+    clip: Rect,
+    damage: Option<Rect>,
+}
+
+impl<'a, S: Surface> Canvas<'a, S> {
+
+    pub fn new(surface: &'a mut S) -> Canvas<'a, S> {
+        let clip = surface.bounds();
+        return Canvas {
+            surface,
+            clip,
+            damage: None
+        };
+    }
+
+    pub fn clip(&self) -> Rect {
+        return self.clip;
+    }
+
+    pub fn set_clip(&mut self, clip: Rect) {
+        self.clip = clip.intersection(&self.surface.bounds()).unwrap_or(Rect::new(0, 0, 0, 0));
+    }
+
+    pub fn damage(&self) -> Option<Rect> {
+        return self.damage;
+    }
+
+    fn mark(&mut self, rect: Rect) {
+        if let Some(rect) = rect.intersection(&self.clip) {
+            self.damage = Some(match self.damage {
+                Some(damage) => damage.union(&rect),
+                None => rect
+            });
+        }
+    }
+
+    pub fn put_pixel(&mut self, x: i32, y: i32, color: u32) {
+        if self.clip.contains(x, y) {
+            self.surface.set_pixel(x as usize, y as usize, color);
+            self.mark(Rect::new(x, y, 1, 1));
+        }
+    }
+
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u32) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+        loop {
+            if self.clip.contains(x, y) {
+                self.surface.set_pixel(x as usize, y as usize, color);
+            }
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error = error + dy;
+                x = x + step_x;
+            }
+            if doubled <= dx {
+                error = error + dx;
+                y = y + step_y;
+            }
+        }
+        let bounds = Rect::new(x0.min(x1), y0.min(y1), dx as u32 + 1, (-dy) as u32 + 1);
+        self.mark(bounds);
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: u32) {
+        let area = match rect.intersection(&self.clip) {
+            Some(area) => area,
+            None => return
+        };
+        for y in area.y..area.bottom() {
+            for x in area.x..area.right() {
+                self.surface.set_pixel(x as usize, y as usize, color);
+            }
+        }
+        self.mark(area);
+    }
+
+    pub fn draw_rect(&mut self, rect: Rect, color: u32) {
+        if rect.is_empty() {
+            return;
+        }
+        let (width, height) = (rect.width, rect.height);
+        self.fill_rect(Rect::new(rect.x, rect.y, width, 1), color);
+        self.fill_rect(Rect::new(rect.x, rect.bottom() - 1, width, 1), color);
+        self.fill_rect(Rect::new(rect.x, rect.y, 1, height), color);
+        self.fill_rect(Rect::new(rect.right() - 1, rect.y, 1, height), color);
+    }
+
+    pub fn clear(&mut self, color: u32) {
+        let clip = self.clip;
+        self.fill_rect(clip, color);
+    }
+
+    // alpha blends the bitmap with its top left corner at (x, y)
+    pub fn blit(&mut self, bitmap: &Bitmap, x: i32, y: i32) {
+        let target = Rect::new(x, y, bitmap.width as u32, bitmap.height as u32);
+        let area = match target.intersection(&self.clip) {
+            Some(area) => area,
+            None => return
+        };
+        for py in area.y..area.bottom() {
+            let row = (py - y) as usize * bitmap.width;
+            for px in area.x..area.right() {
+                let source = bitmap.pixels[row + (px - x) as usize];
+                let destination = self.surface.pixel(px as usize, py as usize);
+                self.surface.set_pixel(px as usize, py as usize, blend(destination, source));
+            }
+        }
+        self.mark(area);
+    }
+}
+
+#[test_case]
+fn test_fill_rect_is_clipped() {
+    use super::MemorySurface;
+
+    let mut surface = MemorySurface::new(8, 8, 0);
+    let mut canvas = Canvas::new(&mut surface);
+    canvas.set_clip(Rect::new(2, 2, 4, 4));
+    canvas.fill_rect(Rect::new(-5, -5, 100, 100), 0xff);
+    assert_eq!(canvas.damage(), Some(Rect::new(2, 2, 4, 4)));
+    assert_eq!(surface.pixel(1, 1), 0);
+    assert_eq!(surface.pixel(2, 2), 0xff);
+    assert_eq!(surface.pixel(5, 5), 0xff);
+    assert_eq!(surface.pixel(6, 6), 0);
+}
+
+#[test_case]
+fn test_draw_line_and_rect() {
+    use super::MemorySurface;
+
+    let mut surface = MemorySurface::new(8, 8, 0);
+    let mut canvas = Canvas::new(&mut surface);
+    canvas.draw_line(0, 0, 7, 7, 1);
+    canvas.draw_rect(Rect::new(1, 1, 3, 3), 2);
+    for i in 0..8 {
+        if i < 1 || i > 3 {
+            assert_eq!(surface.pixel(i, i), 1);
+        }
+    }
+    assert_eq!(surface.pixel(3, 1), 2);
+    assert_eq!(surface.pixel(2, 2), 1);
+    assert_eq!(surface.pixel(1, 3), 2);
+}
+
+#[test_case]
+fn test_blit_blends_alpha() {
+    use super::{rgb, rgba, MemorySurface};
+
+    let pixels = [rgba(255, 0, 0, 255), rgba(0, 0, 255, 0)];
+    let bitmap = Bitmap { width: 2, height: 1, pixels: &pixels };
+    let mut surface = MemorySurface::new(4, 4, rgb(0, 255, 0));
+    let mut canvas = Canvas::new(&mut surface);
+    canvas.blit(&bitmap, 3, 3);
+    assert_eq!(canvas.damage(), Some(Rect::new(3, 3, 1, 1)));
+    assert_eq!(surface.pixel(3, 3), rgb(255, 0, 0));
+    assert_eq!(surface.pixel(2, 3), rgb(0, 255, 0));
+}
@@ -0,0 +1,103 @@
+use super::{Canvas, MemorySurface, Rect, Surface};
+
+use alloc::vec::Vec;
+
+// past this many regions a flush copies their bounding box instead
+const MAX_DIRTY_RECTS: usize = 16;
+
+// draws into memory and only copies the changed regions to the front surface
+pub struct DoubleBuffer<S: Surface> {
+    front: S,
+    back: MemorySurface,
+    dirty: Vec<Rect>,
+}
+
+impl<S: Surface> DoubleBuffer<S> {
+
+    pub fn new(front: S) -> DoubleBuffer<S> {
+        let mut back = MemorySurface::new(front.width(), front.height(), 0);
+        for y in 0..front.height() {
+            for x in 0..front.width() {
+                back.set_pixel(x, y, front.pixel(x, y));
+            }
+        }
+        return DoubleBuffer {
+            front,
+            back,
+            dirty: Vec::new()
+        };
+    }
+
+    pub fn front(&mut self) -> &mut S {
+        return &mut self.front;
+    }
+
+    pub fn back(&self) -> &MemorySurface {
+        return &self.back;
+    }
+
+    pub fn dirty_rects(&self) -> &[Rect] {
+        return &self.dirty;
+    }
+
+    pub fn draw<F: FnOnce(&mut Canvas<MemorySurface>)>(&mut self, draw: F) {
+        let mut canvas = Canvas::new(&mut self.back);
+        draw(&mut canvas);
+        if let Some(damage) = canvas.damage() {
+            self.mark_dirty(damage);
+        }
+    }
+
+    pub fn mark_dirty(&mut self, rect: Rect) {
+        let mut rect = match rect.intersection(&self.back.bounds()) {
+            Some(rect) => rect,
+            None => return
+        };
+        // overlapping regions are merged so no pixel is copied twice
+        while let Some(index) = self.dirty.iter().position(|dirty| dirty.intersects(&rect)) {
+            rect = rect.union(&self.dirty.swap_remove(index));
+        }
+        self.dirty.push(rect);
+        if self.dirty.len() > MAX_DIRTY_RECTS {
+            let bounds = self.dirty.iter().fold(Rect::new(0, 0, 0, 0), |bounds, dirty| bounds.union(dirty));
+            self.dirty.clear();
+            self.dirty.push(bounds);
+        }
+    }
+
+    pub fn flush(&mut self) {
+        for rect in self.dirty.drain(..) {
+            let (x, width) = (rect.x as usize, rect.width as usize);
+            for y in rect.y as usize..rect.bottom() as usize {
+                self.front.write_span(x, y, &self.back.row(y)[x..x + width]);
+            }
+        }
+    }
+}
+
+#[test_case]
+fn test_flush_copies_dirty_regions() {
+    let front = MemorySurface::new(16, 16, 0);
+    let mut buffer = DoubleBuffer::new(front);
+    buffer.draw(|canvas| canvas.fill_rect(Rect::new(1, 1, 2, 2), 7));
+    buffer.draw(|canvas| canvas.fill_rect(Rect::new(2, 2, 2, 2), 7));
+    buffer.draw(|canvas| canvas.put_pixel(10, 10, 9));
+    assert_eq!(buffer.dirty_rects().len(), 2);
+    assert_eq!(buffer.front().pixel(1, 1), 0);
+
+    buffer.flush();
+    assert!(buffer.dirty_rects().is_empty());
+    assert_eq!(buffer.front().pixel(1, 1), 7);
+    assert_eq!(buffer.front().pixel(3, 3), 7);
+    assert_eq!(buffer.front().pixel(10, 10), 9);
+    assert_eq!(buffer.front().pixel(5, 5), 0);
+}
+
+#[test_case]
+fn test_many_dirty_regions_collapse() {
+    let mut buffer = DoubleBuffer::new(MemorySurface::new(64, 64, 0));
+    for i in 0..=MAX_DIRTY_RECTS as i32 {
+        buffer.mark_dirty(Rect::new(i * 3, 0, 1, 1));
+    }
+    assert_eq!(buffer.dirty_rects(), &[Rect::new(0, 0, MAX_DIRTY_RECTS as u32 * 3 + 1, 1)]);
+}
@@ -0,0 +1,174 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub mod canvas;
+pub mod double_buffer;
+
+pub use canvas::{Bitmap, Canvas};
+pub use double_buffer::DoubleBuffer;
+
+// colours are 0xaarrggbb, surfaces ignore the alpha byte
+pub const fn rgb(r: u8, g: u8, b: u8) -> u32 {
+    return (r as u32) << 16 | (g as u32) << 8 | b as u32;
+}
+
+pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    return (a as u32) << 24 | rgb(r, g, b);
+}
+
+// draws `source` over `destination` using the source alpha
+pub fn blend(destination: u32, source: u32) -> u32 {
+    let alpha = source >> 24;
+    return match alpha {
+        0 => destination,
+        255 => source & 0x00ff_ffff,
+        _ => {
+            let mut result = 0;
+            for shift in [0, 8, 16].iter() {
+                let src = (source >> shift) & 0xff;
+                let dst = (destination >> shift) & 0xff;
+                let channel = (src * alpha + dst * (255 - alpha)) / 255;
+                result = result | channel << shift;
+            }
+            result
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        return Rect { x, y, width, height };
+    }
+
+    pub fn right(&self) -> i32 {
+        return self.x + self.width as i32;
+    }
+
+    pub fn bottom(&self) -> i32 {
+        return self.y + self.height as i32;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.width == 0 || self.height == 0;
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        return x >= self.x && x < self.right() && y >= self.y && y < self.bottom();
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        return self.intersection(other).is_some();
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        if right <= x || bottom <= y {
+            return None;
+        }
+        return Some(Rect::new(x, y, (right - x) as u32, (bottom - y) as u32));
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+        return Rect::new(x, y, (right - x) as u32, (bottom - y) as u32);
+    }
+}
+
+pub trait Surface {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn pixel(&self, x: usize, y: usize) -> u32;
+    fn set_pixel(&mut self, x: usize, y: usize, color: u32);
+
+    fn write_span(&mut self, x: usize, y: usize, pixels: &[u32]) {
+        for (i, pixel) in pixels.iter().enumerate() {
+            self.set_pixel(x + i, y, *pixel);
+        }
+    }
+
+    fn bounds(&self) -> Rect {
+        return Rect::new(0, 0, self.width() as u32, self.height() as u32);
+    }
+}
+
+// a surface in ordinary memory, used as back buffer and in tests
+pub struct MemorySurface {
+    pixels: Vec<u32>,
+    width: usize,
+    height: usize,
+}
+
+impl MemorySurface {
+
+    pub fn new(width: usize, height: usize, color: u32) -> MemorySurface {
+        return MemorySurface {
+            pixels: vec![color; width * height],
+            width,
+            height
+        };
+    }
+
+    pub fn row(&self, y: usize) -> &[u32] {
+        return &self.pixels[y * self.width..(y + 1) * self.width];
+    }
+}
+
+impl Surface for MemorySurface {
+    fn width(&self) -> usize {
+        return self.width;
+    }
+
+    fn height(&self) -> usize {
+        return self.height;
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> u32 {
+        return self.pixels[y * self.width + x];
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    fn write_span(&mut self, x: usize, y: usize, pixels: &[u32]) {
+        let start = y * self.width + x;
+        self.pixels[start..start + pixels.len()].copy_from_slice(pixels);
+    }
+}
+
+#[test_case]
+fn test_rect_intersection() {
+    let a = Rect::new(0, 0, 10, 10);
+    let b = Rect::new(5, -5, 10, 10);
+    assert_eq!(a.intersection(&b), Some(Rect::new(5, 0, 5, 5)));
+    assert_eq!(a.intersection(&Rect::new(10, 0, 5, 5)), None);
+    assert_eq!(a.union(&b), Rect::new(0, -5, 15, 15));
+}
+
+#[test_case]
+fn test_blend() {
+    assert_eq!(blend(rgb(0, 0, 0), rgba(255, 255, 255, 255)), rgb(255, 255, 255));
+    assert_eq!(blend(rgb(10, 20, 30), rgba(255, 255, 255, 0)), rgb(10, 20, 30));
+    assert_eq!(blend(rgb(0, 0, 0), rgba(254, 0, 0, 128)), rgb(127, 0, 0));
+}
@@ -0,0 +1,108 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::structures::paging::PageTableFlags;
+
+pub const LOCAL_APIC_START: usize = 0x_5555_8000_0000;
+pub const SPURIOUS_VECTOR: u8 = 0xff;
+pub const CALL_FUNCTION_VECTOR: u8 = 0xf0;
+
+const ID: usize = 0x20;
+const EOI: usize = 0xb0;
+const SPURIOUS: usize = 0xf0;
+const ICR_LOW: usize = 0x300;
+const ICR_HIGH: usize = 0x310;
+
+const ICR_INIT: u32 = 0x500;
+const ICR_STARTUP: u32 = 0x600;
+const ICR_LEVEL_ASSERT: u32 = 1 << 14;
+const ICR_DELIVERY_PENDING: u32 = 1 << 12;
+const ICR_ALL: u32 = 0b10 << 18;
+const ICR_ALL_BUT_SELF: u32 = 0b11 << 18;
+
+static MAPPED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApicError {
+    MappingFailed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpiTarget {
+    Apic(u8),
+    All,
+    AllButSelf,
+}
+
+// the registers are shared by every cpu, each one sees its own apic at the same address
+pub fn init(physical_start: PhysAddr) -> Result<(), ApicError> {
+    if MAPPED.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_CACHE;
+    crate::memory::map_physical_region(
+        physical_start,
+        4096,
+        VirtAddr::new(LOCAL_APIC_START as u64),
+        flags
+    ).map_err(|_| ApicError::MappingFailed)?;
+    MAPPED.store(true, Ordering::Release);
+    return Ok(());
+}
+
+pub fn is_mapped() -> bool {
+    return MAPPED.load(Ordering::Acquire);
+}
+
+// software enable, spurious interrupts land on a vector that just returns
+pub fn enable() {
+    unsafe {
+        write(SPURIOUS, 0x100 | SPURIOUS_VECTOR as u32);
+    }
+}
+
+pub fn id() -> u8 {
+    return unsafe { (read(ID) >> 24) as u8 };
+}
+
+pub unsafe fn eoi() {
+    write(EOI, 0);
+}
+
+pub unsafe fn send_init(apic_id: u8) {
+    send(apic_id, ICR_INIT | ICR_LEVEL_ASSERT);
+}
+
+// the target starts in real mode at vector * 4096
+pub unsafe fn send_startup(apic_id: u8, vector: u8) {
+    send(apic_id, ICR_STARTUP | vector as u32);
+}
+
+// a fixed interrupt on `vector`, the targets need a handler that sends an eoi
+pub unsafe fn send_ipi(target: IpiTarget, vector: u8) {
+    match target {
+        IpiTarget::Apic(apic_id) => send(apic_id, vector as u32),
+        IpiTarget::All => send(0, ICR_ALL | vector as u32),
+        IpiTarget::AllButSelf => send(0, ICR_ALL_BUT_SELF | vector as u32)
+    }
+}
+
+unsafe fn send(apic_id: u8, command: u32) {
+    // a handler sending its own ipi between the two writes would change the destination
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        write(ICR_HIGH, (apic_id as u32) << 24);
+        // writing the low half sends it
+        write(ICR_LOW, command);
+        while read(ICR_LOW) & ICR_DELIVERY_PENDING != 0 {
+            core::sync::atomic::spin_loop_hint();
+        }
+    });
+}
+
+unsafe fn read(register: usize) -> u32 {
+    return core::ptr::read_volatile((LOCAL_APIC_START + register) as *const u32);
+}
+
+unsafe fn write(register: usize, value: u32) {
+    core::ptr::write_volatile((LOCAL_APIC_START + register) as *mut u32, value);
+}
@@ -0,0 +1,136 @@
+use super::{Key, KeyEvent, Modifiers};
+
+use spin::Mutex;
+
+const MAX_BINDINGS: usize = 32;
+
+pub type Handler = fn(KeyEvent);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl KeyCombo {
+
+    pub const fn new(modifiers: Modifiers, key: Key) -> KeyCombo {
+        return KeyCombo { modifiers, key };
+    }
+
+    fn matches(&self, event: &KeyEvent) -> bool {
+        return self.modifiers == normalize_modifiers(event.modifiers) && normalize(self.key) == normalize(event.key);
+    }
+}
+
+// right alt is altgr on the international layout, but still alt to a binding. most
+// keyboards only send sysrq together with alt, so the sysrq combos are bound without it
+fn normalize_modifiers(modifiers: Modifiers) -> Modifiers {
+    let mut modifiers = modifiers;
+    if modifiers.contains(Modifiers::ALTGR) {
+        modifiers = modifiers.without(Modifiers::ALTGR) | Modifiers::ALT;
+    }
+    if modifiers.contains(Modifiers::SYSRQ) {
+        modifiers = modifiers.without(Modifiers::ALT);
+    }
+    return modifiers;
+}
+
+// shift and capslock change the character, but not which binding is meant
+fn normalize(key: Key) -> Key {
+    return match key {
+        Key::Char(c) => Key::Char(c.to_ascii_lowercase()),
+        key => key
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingError {
+    AlreadyBound,
+    TableFull,
+}
+
+#[derive(Clone, Copy)]
+struct Binding {
+    combo: KeyCombo,
+    handler: Handler,
+}
+
+static BINDINGS: Mutex<[Option<Binding>; MAX_BINDINGS]> = Mutex::new([None; MAX_BINDINGS]);
+
+pub fn register(combo: KeyCombo, handler: Handler) -> Result<(), BindingError> {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut bindings = BINDINGS.lock();
+        if bindings.iter().flatten().any(|binding| binding.combo == combo) {
+            return Err(BindingError::AlreadyBound);
+        }
+        let slot = bindings.iter_mut().find(|slot| slot.is_none()).ok_or(BindingError::TableFull)?;
+        *slot = Some(Binding { combo, handler });
+        return Ok(());
+    });
+}
+
+pub fn unregister(combo: KeyCombo) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        for slot in BINDINGS.lock().iter_mut() {
+            if slot.map_or(false, |binding| binding.combo == combo) {
+                *slot = None;
+            }
+        }
+    });
+}
+
+// runs the handler bound to the event, returns false when nothing matched
+pub fn trigger(event: KeyEvent) -> bool {
+    let handler = x86_64::instructions::interrupts::without_interrupts(|| {
+        return BINDINGS.lock().iter()
+            .flatten()
+            .find(|binding| binding.combo.matches(&event))
+            .map(|binding| binding.handler);
+    });
+    return match handler {
+        Some(handler) => {
+            handler(event);
+            true
+        },
+        None => false
+    };
+}
+
+#[test_case]
+fn test_binding_from_scancodes() {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    static FIRED: AtomicBool = AtomicBool::new(false);
+
+    let combo = KeyCombo::new(Modifiers::CTRL | Modifiers::ALT, Key::Char('k'));
+    register(combo, |_| FIRED.store(true, Ordering::SeqCst)).unwrap();
+    assert_eq!(register(combo, |_| {}), Err(BindingError::AlreadyBound));
+
+    super::simulate(&[0x1d, 0x38, 0x25, 0xa5, 0xb8, 0x9d]);
+    assert!(FIRED.load(Ordering::SeqCst));
+
+    FIRED.store(false, Ordering::SeqCst);
+    unregister(combo);
+    super::simulate(&[0x1d, 0x38, 0x25, 0xa5, 0xb8, 0x9d]);
+    assert!(!FIRED.load(Ordering::SeqCst));
+}
+
+#[test_case]
+fn test_binding_ignores_case() {
+    let combo = KeyCombo::new(Modifiers::CTRL, Key::Char('c'));
+    let event = KeyEvent { key: Key::Char('C'), modifiers: Modifiers::CTRL };
+    assert!(combo.matches(&event));
+    let event = KeyEvent { key: Key::Char('c'), modifiers: Modifiers::CTRL | Modifiers::SHIFT };
+    assert!(!combo.matches(&event));
+}
+
+#[test_case]
+fn test_binding_treats_altgr_as_alt() {
+    let combo = KeyCombo::new(Modifiers::CTRL | Modifiers::ALT, Key::Delete);
+    let event = KeyEvent { key: Key::Delete, modifiers: Modifiers::CTRL | Modifiers::ALTGR };
+    assert!(combo.matches(&event));
+    let combo = KeyCombo::new(Modifiers::ALT, Key::F(2));
+    let event = KeyEvent { key: Key::F(2), modifiers: Modifiers::ALTGR };
+    assert!(combo.matches(&event));
+}
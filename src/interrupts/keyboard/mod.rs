@@ -0,0 +1,565 @@
+use crate::sync::SpinLock;
+use crate::trace;
+use crate::vga_buffer::CONSOLES;
+use super::{eoi, ps2, InterruptIndex};
+use super::trap::TrapFrame;
+
+use core::ops::BitOr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::lazy_static;
+
+pub mod bindings;
+mod sysrq;
+use bindings::KeyCombo;
+
+// typematic timings in timer ticks, the pit fires at ~18.2Hz
+const DEFAULT_REPEAT_DELAY: u32 = 9;
+const DEFAULT_REPEAT_INTERVAL: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    F(u8),
+    SysRq,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers(0);
+    pub const SHIFT: Modifiers = Modifiers(0x01);
+    pub const CTRL: Modifiers = Modifiers(0x02);
+    pub const ALT: Modifiers = Modifiers(0x04);
+    pub const SYSRQ: Modifiers = Modifiers(0x08);
+    pub const ALTGR: Modifiers = Modifiers(0x10);
+
+    pub fn contains(&self, other: Modifiers) -> bool {
+        return self.0 & other.0 == other.0;
+    }
+
+    pub fn without(&self, other: Modifiers) -> Modifiers {
+        return Modifiers(self.0 & !other.0);
+    }
+
+    fn set(&mut self, other: Modifiers, pressed: bool) {
+        if pressed {
+            self.0 = self.0 | other.0;
+        }
+        else {
+            self.0 = self.0 & !other.0;
+        }
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        return Modifiers(self.0 | other.0);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Us,
+    // right alt (altgr) produces accented latin letters
+    UsInternational,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+struct Repeat {
+    event: KeyEvent,
+    code: u8,
+    ticks: u32,
+}
+
+struct Keyboard {
+    layout: Layout,
+    capslock: bool,
+    numlock: bool,
+    modifiers: Modifiers,
+    extended: bool,
+    repeat: Option<Repeat>,
+    repeat_delay: u32,
+    repeat_interval: u32,
+}
+
+lazy_static! {
+    static ref KEYBOARD: SpinLock<Keyboard> = SpinLock::named("keyboard", Keyboard::new());
+}
+
+impl Keyboard {
+
+    fn new() -> Keyboard {
+        return Keyboard {
+            layout: Layout::UsInternational,
+            capslock: false,
+            numlock: false,
+            modifiers: Modifiers::NONE,
+            extended: false,
+            repeat: None,
+            repeat_delay: DEFAULT_REPEAT_DELAY,
+            repeat_interval: DEFAULT_REPEAT_INTERVAL
+        }
+    }
+
+    fn shifted(&self) -> bool {
+        return self.modifiers.contains(Modifiers::SHIFT);
+    }
+
+    fn process(&mut self, code: u8) -> Option<KeyEvent> {
+        if code == 0xe0 {
+            self.extended = true;
+            return None;
+        }
+        let extended = self.extended;
+        self.extended = false;
+        let released = code & 0x80 != 0;
+        let make = code & 0x7f;
+
+        let modifier = match (make, extended) {
+            (0x2a, true) | (0x36, true) => return None, // fake shifts around extended keys
+            (0x2a, false) | (0x36, false) => Some(Modifiers::SHIFT),
+            (0x1d, _) => Some(Modifiers::CTRL),
+            (0x38, false) => Some(Modifiers::ALT),
+            (0x38, true) => Some(Modifiers::ALTGR),
+            (0x37, true) | (0x54, false) => Some(Modifiers::SYSRQ),
+            _ => None
+        };
+        if let Some(modifier) = modifier {
+            // sysrq is also a key of its own, reported once when it goes down
+            let sysrq = modifier == Modifiers::SYSRQ && !released && !self.modifiers.contains(Modifiers::SYSRQ);
+            self.modifiers.set(modifier, !released);
+            if sysrq {
+                return Some(KeyEvent { key: Key::SysRq, modifiers: self.modifiers });
+            }
+            return None;
+        }
+
+        if released {
+            if make == 0x3a { // capslock (binded to key release)
+                self.capslock = !self.capslock;
+            }
+            if make == 0x45 && !extended {
+                self.numlock = !self.numlock;
+            }
+            if self.repeat.as_ref().map_or(false, |repeat| repeat.code == make) {
+                self.repeat = None;
+            }
+            return None;
+        }
+
+        // the hardware typematic repeat is ignored, keys are repeated from the timer instead
+        if self.repeat.as_ref().map_or(false, |repeat| repeat.code == make) {
+            return None;
+        }
+
+        let key = self.key(make, extended)?;
+        let event = KeyEvent { key, modifiers: self.modifiers };
+        self.repeat = Some(Repeat { event, code: make, ticks: 0 });
+        return Some(event);
+    }
+
+    fn tick(&mut self) -> Option<KeyEvent> {
+        let delay = self.repeat_delay;
+        let interval = self.repeat_interval;
+        let repeat = self.repeat.as_mut()?;
+        repeat.ticks = repeat.ticks + 1;
+        if repeat.ticks >= delay && (repeat.ticks - delay) % interval == 0 {
+            return Some(repeat.event);
+        }
+        return None;
+    }
+
+    fn key(&self, code: u8, extended: bool) -> Option<Key> {
+        return match (code, extended) {
+            (0x01, _) => Some(Key::Escape),
+            (0x0e, _) => Some(Key::Backspace),
+            (0x1c, true) => Some(Key::Char('\n')), // keypad enter
+            (0x35, true) => Some(Key::Char('/')), // keypad slash
+            (0x3b..=0x44, _) => Some(Key::F(code - 0x3a)),
+            (0x57, _) => Some(Key::F(11)),
+            (0x58, _) => Some(Key::F(12)),
+            (0x47, _) => Some(Key::Home),
+            (0x48, _) => Some(Key::Up),
+            (0x49, _) => Some(Key::PageUp),
+            (0x4b, _) => Some(Key::Left),
+            (0x4d, _) => Some(Key::Right),
+            (0x4f, _) => Some(Key::End),
+            (0x50, _) => Some(Key::Down),
+            (0x51, _) => Some(Key::PageDown),
+            (0x52, _) => Some(Key::Insert),
+            (0x53, _) => Some(Key::Delete),
+            (_, _) => self.character(code).map(Key::Char)
+        };
+    }
+
+    fn character(&self, code: u8) -> Option<char> {
+        if self.layout == Layout::UsInternational && self.modifiers.contains(Modifiers::ALTGR) {
+            return self.altgr_character(code);
+        }
+        return match (code, self.capslock, self.shifted()) {
+            (0x02..=0x0d, _, false) => match code {
+                0x02 => Some('1'),
+                0x03 => Some('2'),
+                0x04 => Some('3'),
+                0x05 => Some('4'),
+                0x06 => Some('5'),
+                0x07 => Some('6'),
+                0x08 => Some('7'),
+                0x09 => Some('8'),
+                0x0a => Some('9'),
+                0x0b => Some('0'),
+                0x0C => Some('-'),
+                0x0D => Some('='),
+                _ => unreachable!()
+            },
+            (0x02..=0x0d, _, true) => match code {
+                0x02 => Some('!'),
+                0x03 => Some('@'),
+                0x04 => Some('#'),
+                0x05 => Some('$'),
+                0x06 => Some('%'),
+                0x07 => Some('^'),
+                0x08 => Some('&'),
+                0x09 => Some('*'),
+                0x0a => Some('('),
+                0x0b => Some(')'),
+                0x0c => Some('_'),
+                0x0d => Some('+'),
+                _ => unreachable!()
+            },
+            (0x0F, _, _) => Some('\t'), // tab
+            (0x1C, _, _) => Some('\n'), // enter
+            (0x10..=0x19 | 0x1e..=0x26 | 0x2c..=0x32, false, false) => match code {
+                0x10 => Some('q'),
+                0x11 => Some('w'),
+                0x12 => Some('e'),
+                0x13 => Some('r'),
+                0x14 => Some('t'),
+                0x15 => Some('y'),
+                0x16 => Some('u'),
+                0x17 => Some('i'),
+                0x18 => Some('o'),
+                0x19 => Some('p'),
+
+                0x1E => Some('a'),
+                0x1F => Some('s'),
+                0x20 => Some('d'),
+                0x21 => Some('f'),
+                0x22 => Some('g'),
+                0x23 => Some('h'),
+                0x24 => Some('j'),
+                0x25 => Some('k'),
+                0x26 => Some('l'),
+            
+                0x2C => Some('z'),
+                0x2D => Some('x'),
+                0x2E => Some('c'),
+                0x2F => Some('v'),
+                0x30 => Some('b'),
+                0x31 => Some('n'),
+                0x32 => Some('m'),
+                _ => unreachable!()
+            },
+            (0x10..=0x19 | 0x1e..=0x26 | 0x2c..=0x32, true, _) | (0x10..=0x19 | 0x1e..=0x26 | 0x2c..=0x32, _, true) => match code {
+                0x10 => Some('Q'),
+                0x11 => Some('W'),
+                0x12 => Some('E'),
+                0x13 => Some('R'),
+                0x14 => Some('T'),
+                0x15 => Some('Y'),
+                0x16 => Some('U'),
+                0x17 => Some('I'),
+                0x18 => Some('O'),
+                0x19 => Some('P'),
+
+                0x1E => Some('A'),
+                0x1F => Some('S'),
+                0x20 => Some('D'),
+                0x21 => Some('F'),
+                0x22 => Some('G'),
+                0x23 => Some('H'),
+                0x24 => Some('J'),
+                0x25 => Some('K'),
+                0x26 => Some('L'),
+
+                0x2C => Some('Z'),
+                0x2D => Some('X'),
+                0x2E => Some('C'),
+                0x2F => Some('V'),
+                0x30 => Some('B'),
+                0x31 => Some('N'),
+                0x32 => Some('M'),
+                _ => unreachable!()
+            },
+            (0x1A, _, false) => Some('['),
+            (0x1A, _, true) => Some('{'),
+            (0x1B, _, false) => Some(']'),
+            (0x1B, _, true) => Some('}'),
+            (0x33, _, false) => Some(','),
+            (0x33, _, true) => Some('<'),
+            (0x34, _, false) => Some('.'),
+            (0x34, _, true) => Some('>'),
+            (0x27, _, false) => Some(';'),
+            (0x27, _, true) => Some(':'),
+            (0x28, _, false) => Some('\''),
+            (0x28, _, true) => Some('"'),
+            (0x29, _, false) => Some('`'),
+            (0x29, _, true) => Some('~'),
+            (0x2B, _, false) => Some('\\'),
+            (0x2B, _, true) => Some('|'),
+            (0x35, _, false) => Some('/'),
+            (0x35, _, true) => Some('?'),
+            (0x39, _, _) => Some(' '),
+            (_, _, _) => None
+        }
+    }
+
+    fn altgr_character(&self, code: u8) -> Option<char> {
+        let upper = self.capslock != self.shifted();
+        return match (code, upper) {
+            (0x02, _) => Some('¡'),
+            (0x0c, _) => Some('¥'),
+            (0x0d, _) => Some('÷'),
+            (0x35, _) => Some('¿'),
+            (0x10, false) => Some('ä'),
+            (0x10, true) => Some('Ä'),
+            (0x12, false) => Some('é'),
+            (0x12, true) => Some('É'),
+            (0x15, false) => Some('ü'),
+            (0x15, true) => Some('Ü'),
+            (0x16, _) => Some('ú'),
+            (0x17, _) => Some('í'),
+            (0x18, _) => Some('ó'),
+            (0x19, false) => Some('ö'),
+            (0x19, true) => Some('Ö'),
+            (0x1e, _) => Some('á'),
+            (0x1f, _) => Some('ß'),
+            (0x2c, false) => Some('æ'),
+            (0x2c, true) => Some('Æ'),
+            (0x2e, _) => Some('¢'),
+            (0x31, false) => Some('ñ'),
+            (0x31, true) => Some('Ñ'),
+            (0x33, false) => Some('ç'),
+            (0x33, true) => Some('Ç'),
+            (_, _) => None
+        };
+    }
+}
+
+fn dispatch(event: KeyEvent) {
+    if bindings::trigger(event) {
+        return;
+    }
+    if event.modifiers.contains(Modifiers::CTRL) || event.modifiers.contains(Modifiers::ALT) {
+        return;
+    }
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let console = consoles.active();
+        match event.key {
+            Key::Char(key) => {
+                console.push_input(key);
+                console.write_char(key);
+            },
+            Key::Backspace => console.backspace(),
+            Key::Up => {
+                trace!("arrow up");
+                console.move_up();
+            },
+            Key::Left => {
+                trace!("arrow left");
+                console.move_left();
+            },
+            Key::Right => {
+                trace!("arrow right");
+                console.move_right();
+            },
+            Key::Down => {
+                trace!("arrow down");
+                console.move_down();
+            },
+            _ => {}
+        }
+    });
+}
+
+// set by ctrl+c until the task in the foreground takes it and stops what it is doing
+static INTERRUPT: AtomicBool = AtomicBool::new(false);
+
+pub fn take_interrupt() -> bool {
+    return INTERRUPT.swap(false, Ordering::AcqRel);
+}
+
+pub fn init() {
+    let reboot = KeyCombo::new(Modifiers::CTRL | Modifiers::ALT, Key::Delete);
+    bindings::register(reboot, |_| ps2::reset()).expect("ctrl+alt+del already bound");
+    let interrupt = KeyCombo::new(Modifiers::CTRL, Key::Char('c'));
+    bindings::register(interrupt, |_| INTERRUPT.store(true, Ordering::Release)).expect("ctrl+c already bound");
+    sysrq::init();
+}
+
+pub fn modifiers() -> Modifiers {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        return KEYBOARD.lock().modifiers;
+    });
+}
+
+pub fn set_layout(layout: Layout) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        KEYBOARD.lock().layout = layout;
+    });
+}
+
+pub fn capslock() -> bool {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        return KEYBOARD.lock().capslock;
+    });
+}
+
+pub fn numlock() -> bool {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        return KEYBOARD.lock().numlock;
+    });
+}
+
+// capslock and numlock for interrupt context, None while the keyboard is locked
+pub fn try_lock_keys() -> Option<(bool, bool)> {
+    return KEYBOARD.try_lock().map(|keyboard| (keyboard.capslock, keyboard.numlock));
+}
+
+pub fn set_repeat(delay: u32, interval: u32) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut keyboard = KEYBOARD.lock();
+        keyboard.repeat_delay = delay;
+        keyboard.repeat_interval = interval.max(1);
+    });
+}
+
+// feeds scancodes through the same path as the interrupt handler
+pub fn simulate(scancodes: &[u8]) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        for scancode in scancodes {
+            let event = KEYBOARD.lock().process(*scancode);
+            if let Some(event) = event {
+                dispatch(event);
+            }
+        }
+    });
+}
+
+// called on every timer tick to drive the software key repeat
+pub fn tick() {
+    let event = KEYBOARD.lock().tick();
+    if let Some(event) = event {
+        dispatch(event);
+    }
+}
+
+pub(crate) fn keyboard_interrupt_handler(_frame: &mut TrapFrame) {
+    let _irq = super::IrqContext::enter();
+    use x86_64::instructions::port::Port;
+    let mut port = Port::new(0x60);
+    let scancode: u8 = unsafe { port.read() };
+    crate::task::keyboard::add_scancode(scancode);
+    let event = KEYBOARD.lock().process(scancode);
+    if let Some(event) = event {
+        dispatch(event);
+    }
+    unsafe {
+        eoi(InterruptIndex::Keyboard as u8);
+    }
+}
+
+#[test_case]
+fn test_modifiers_are_tracked() {
+    let mut keyboard = Keyboard::new();
+    keyboard.process(0x1d);
+    keyboard.process(0x38);
+    let event = keyboard.process(0x2e).unwrap();
+    assert_eq!(event.key, Key::Char('c'));
+    assert_eq!(event.modifiers, Modifiers::CTRL | Modifiers::ALT);
+    keyboard.process(0x9d);
+    keyboard.process(0xb8);
+    assert_eq!(keyboard.modifiers, Modifiers::NONE);
+}
+
+#[test_case]
+fn test_altgr_characters() {
+    let mut keyboard = Keyboard::new();
+    keyboard.process(0xe0);
+    keyboard.process(0x38);
+    assert_eq!(keyboard.process(0x12).unwrap().key, Key::Char('é'));
+    keyboard.process(0x2a);
+    assert_eq!(keyboard.process(0x33).unwrap().key, Key::Char('Ç'));
+    keyboard.process(0xaa);
+    keyboard.process(0xe0);
+    keyboard.process(0xb8);
+    assert_eq!(keyboard.process(0x12).unwrap().key, Key::Char('e'));
+}
+
+#[test_case]
+fn test_extended_keys() {
+    let mut keyboard = Keyboard::new();
+    keyboard.process(0xe0);
+    assert_eq!(keyboard.process(0x53).unwrap().key, Key::Delete);
+    keyboard.process(0xe0);
+    keyboard.process(0xd3);
+    assert_eq!(keyboard.process(0x3c).unwrap().key, Key::F(2));
+}
+
+#[test_case]
+fn test_software_repeat() {
+    let mut keyboard = Keyboard::new();
+    assert_eq!(keyboard.process(0x1e).unwrap().key, Key::Char('a'));
+    assert_eq!(keyboard.process(0x1e), None);
+    for _ in 1..DEFAULT_REPEAT_DELAY {
+        assert_eq!(keyboard.tick(), None);
+    }
+    assert_eq!(keyboard.tick().unwrap().key, Key::Char('a'));
+    assert_eq!(keyboard.tick().unwrap().key, Key::Char('a'));
+    keyboard.process(0x9e);
+    assert_eq!(keyboard.tick(), None);
+}
+
+#[test_case]
+fn test_sysrq_is_a_key() {
+    let mut keyboard = Keyboard::new();
+    keyboard.process(0x38);
+    let event = keyboard.process(0x54).unwrap();
+    assert_eq!(event.key, Key::SysRq);
+    assert_eq!(event.modifiers, Modifiers::ALT | Modifiers::SYSRQ);
+    // the typematic repeat of a held sysrq is not another press
+    assert_eq!(keyboard.process(0x54), None);
+    assert_eq!(keyboard.process(0x14).unwrap().modifiers, Modifiers::ALT | Modifiers::SYSRQ);
+    keyboard.process(0xd4);
+    keyboard.process(0xb8);
+    assert_eq!(keyboard.modifiers, Modifiers::NONE);
+}
+
+#[test_case]
+fn test_ctrl_c_interrupts() {
+    take_interrupt();
+    simulate(&[0x1d, 0x2e, 0xae, 0x9d]);
+    assert!(take_interrupt());
+    assert!(!take_interrupt());
+}
@@ -0,0 +1,56 @@
+// magic sysrq combos, held sysrq (alt+print screen) and a letter. the handlers run in
+// the keyboard interrupt, so anything that may be locked is only tried
+
+use super::bindings::{self, KeyCombo};
+use super::{Key, KeyEvent, Modifiers};
+use crate::{info, memory, thread};
+
+pub fn init() {
+    let threads = KeyCombo::new(Modifiers::SYSRQ, Key::Char('t'));
+    bindings::register(threads, dump_threads).expect("sysrq+t already bound");
+    let memory = KeyCombo::new(Modifiers::SYSRQ, Key::Char('m'));
+    bindings::register(memory, dump_memory).expect("sysrq+m already bound");
+}
+
+fn dump_threads(_event: KeyEvent) {
+    info!("sysrq: threads");
+    for stats in thread::all_stats().iter().flatten() {
+        info!("  {:?} {:?} nice {} cpu {} ms", stats.id, stats.state, stats.nice, stats.cpu_time_ms);
+    }
+}
+
+fn dump_memory(_event: KeyEvent) {
+    info!("sysrq: memory");
+    match memory::allocator::try_usage() {
+        Some((used, free)) => info!("  heap {} bytes used, {} free", used, free),
+        None => info!("  heap is locked")
+    }
+    let free_frames = memory::FRAME_ALLOCATOR.try_lock()
+        .and_then(|frame_allocator| frame_allocator.as_ref().map(|frame_allocator| frame_allocator.free_frames()));
+    match free_frames {
+        Some(free_frames) => info!("  {} free frames", free_frames),
+        None => info!("  frame allocator is locked")
+    }
+}
+
+#[cfg(test)]
+fn logged(message: &str) -> bool {
+    use alloc::string::String;
+
+    let mut log = String::new();
+    crate::log::dmesg(&mut log).unwrap();
+    return log.contains(message);
+}
+
+#[test_case]
+fn test_sysrq_dumps_threads() {
+    // alt, sysrq, t and back up
+    super::simulate(&[0x38, 0x54, 0x14, 0x94, 0xd4, 0xb8]);
+    assert!(logged("sysrq: threads"));
+}
+
+#[test_case]
+fn test_sysrq_dumps_memory() {
+    super::simulate(&[0x38, 0x54, 0x32, 0xb2, 0xd4, 0xb8]);
+    assert!(logged("sysrq: memory"));
+}
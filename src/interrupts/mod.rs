@@ -1,27 +1,51 @@
 use crate::{println,halt};
 use crate::gdt;
+use crate::cpu_local;
+use crate::smp::percpu;
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU64, Ordering};
 
 use lazy_static::lazy_static;
-use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
+use x86_64::structures::idt::{InterruptDescriptorTable, PageFaultErrorCode};
 
 use pic8259_simple::ChainedPics;
 use spin::Mutex;
 
-mod keyboard;
-use keyboard::keyboard_interrupt_handler;
+pub mod apic;
+pub mod keyboard;
+mod ps2;
+pub mod mouse;
+pub mod trap;
+use trap::TrapFrame;
 
 lazy_static! {
     static ref IDT: InterruptDescriptorTable = {
+        // every handler runs behind a stub in `trap`, which keeps the gs base straight
         let mut idt = InterruptDescriptorTable::new();
-        idt.breakpoint.set_handler_fn(breakpoint_handler);
-        idt.page_fault.set_handler_fn(page_fault_handler);
+        idt.debug.set_handler_fn(trap::entry(trap::DEBUG_VECTOR));
+        // int3 stays usable from ring 3
+        idt.breakpoint
+            .set_handler_fn(trap::entry(trap::BREAKPOINT_VECTOR))
+            .set_privilege_level(x86_64::PrivilegeLevel::Ring3);
+        idt.page_fault.set_handler_fn(trap::page_fault_entry());
         unsafe {
             idt.double_fault
-                .set_handler_fn(double_fault_handler)
+                .set_handler_fn(trap::double_fault_entry())
                 .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
         }
-        idt[InterruptIndex::Timer as usize].set_handler_fn(timer_interrupt_handler);
-        idt[InterruptIndex::Keyboard as usize].set_handler_fn(keyboard_interrupt_handler);
+        let vectors = [
+            InterruptIndex::Timer as u8,
+            InterruptIndex::Keyboard as u8,
+            InterruptIndex::Com2 as u8,
+            InterruptIndex::Com1 as u8,
+            InterruptIndex::Mouse as u8,
+            apic::CALL_FUNCTION_VECTOR,
+            apic::SPURIOUS_VECTOR
+        ];
+        for vector in vectors.iter() {
+            idt[*vector as usize].set_handler_fn(trap::entry(*vector as u64));
+        }
         return idt;
     };
 }
@@ -30,25 +54,53 @@ pub fn init_idt() {
     IDT.load();
 }
 
-extern "x86-interrupt" fn breakpoint_handler(stack_frame: &mut InterruptStackFrame) {
-    println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
+// the frame of the last int3, a debugger that owns the trap doesn't update it
+static LAST_BREAKPOINT: Mutex<Option<TrapFrame>> = Mutex::new(None);
+
+pub fn last_breakpoint() -> Option<TrapFrame> {
+    return *LAST_BREAKPOINT.lock();
+}
+
+fn breakpoint_handler(frame: &mut TrapFrame) {
+    if crate::gdb::handle_trap(frame) {
+        return;
+    }
+    if let Some(mut last) = LAST_BREAKPOINT.try_lock() {
+        *last = Some(*frame);
+    }
+    println!("EXCEPTION: BREAKPOINT\n{:#?}", frame);
+}
+
+fn debug_handler(frame: &mut TrapFrame) {
+    if crate::gdb::handle_trap(frame) {
+        return;
+    }
+    // a stray trap flag would fire again on every instruction
+    frame.rflags = frame.rflags & !trap::TRAP_FLAG;
+    println!("EXCEPTION: DEBUG\n{:#?}", frame);
 }
 
-extern "x86-interrupt" fn page_fault_handler(stack_frame: &mut InterruptStackFrame, error_code: PageFaultErrorCode) {
+fn page_fault_handler(frame: &mut TrapFrame) {
     println!("EXCEPTION: PAGE FAULT");
     println!("Accessed Address: {:?}", x86_64::registers::control::Cr2::read());
-    println!("Error Code: {:?}", error_code);
-    println!("{:#?}", stack_frame);
+    println!("Error Code: {:?}", PageFaultErrorCode::from_bits_truncate(frame.error_code));
+    println!("{:#?}", frame);
     halt();
 }
 
-extern "x86-interrupt" fn double_fault_handler(stack_frame: &mut InterruptStackFrame, _error_code: u64) -> ! {
-    panic!("EXCEPTION: DOUBLE FAULT\n{:#?}", stack_frame);
+// the local apic expects no eoi for these
+fn spurious_interrupt_handler(_frame: &mut TrapFrame) {}
+
+fn double_fault_handler(frame: &mut TrapFrame) -> ! {
+    panic!("EXCEPTION: DOUBLE FAULT\n{:#?}", frame);
 }
 
 #[test_case]
 fn test_breakpoint_exception() {
     x86_64::instructions::interrupts::int3();
+    let frame = last_breakpoint().expect("breakpoint not recorded");
+    assert_eq!(frame.vector, trap::BREAKPOINT_VECTOR);
+    assert_eq!(frame.cs & 3, 0);
 }
 
 pub const PIC_1_OFFSET: u8 = 32;
@@ -59,15 +111,109 @@ pub static PICS: Mutex<ChainedPics> = Mutex::new(unsafe { ChainedPics::new(PIC_1
 #[repr(u8)]
 pub enum InterruptIndex {
     Timer = PIC_1_OFFSET,
-    Keyboard
+    Keyboard,
+    Com2 = PIC_1_OFFSET + 3,
+    Com1,
+    Mouse = PIC_2_OFFSET + 4
 }
 
 pub unsafe fn eoi(code: u8) {
     PICS.lock().notify_end_of_interrupt(code);
 }
 
-extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: &mut InterruptStackFrame) {
+// for code that runs without initializing the pics, they still use the bios vectors,
+// which would land on exceptions once interrupts are enabled
+pub fn mask_pics() {
+    use x86_64::instructions::port::Port;
+
+    unsafe {
+        Port::<u8>::new(0x21).write(0xff);
+        Port::<u8>::new(0xa1).write(0xff);
+    }
+}
+
+// the firmware may leave some lines masked, so drivers unmask the ones they use
+pub fn unmask_irq(irq: u8) {
+    use x86_64::instructions::port::Port;
+
+    let (port, line) = match irq {
+        0..=7 => (0x21, irq),
+        _ => {
+            unmask_irq(2); // cascade line from the secondary pic
+            (0xa1, irq - 8)
+        }
+    };
+    let mut port: Port<u8> = Port::new(port);
+    unsafe {
+        let mask = port.read();
+        port.write(mask & !(1 << line));
+    }
+}
+
+// the pit runs at its power-on rate, one tick every 65536 input clocks
+const PIT_FREQUENCY: u64 = 1_193_182;
+const PIT_DIVISOR: u64 = 65536;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+cpu_local! {
+    // each cpu takes its own interrupts, a handler on one says nothing about the others
+    static IRQ_DEPTH: Cell<usize> = Cell::new(0);
+}
+
+// held by hardware interrupt handlers for as long as they run, see `in_interrupt`
+pub(crate) struct IrqContext(());
+
+impl IrqContext {
+    pub(crate) fn enter() -> IrqContext {
+        IRQ_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        return IrqContext(());
+    }
+}
+
+impl Drop for IrqContext {
+    fn drop(&mut self) {
+        IRQ_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+pub fn in_interrupt() -> bool {
+    // no handler can run before the per-cpu blocks are there
+    if !percpu::is_loaded() {
+        return false;
+    }
+    return IRQ_DEPTH.get().get() > 0;
+}
+
+pub fn ticks() -> u64 {
+    return TICKS.load(Ordering::Relaxed);
+}
+
+pub fn uptime_ms() -> u64 {
+    return ticks_to_ms(ticks());
+}
+
+pub fn ticks_to_ms(ticks: u64) -> u64 {
+    return ticks * PIT_DIVISOR * 1000 / PIT_FREQUENCY;
+}
+
+// rounded up, a tick is about 55 ms
+pub fn ms_to_ticks(ms: u64) -> u64 {
+    let clocks = ms * PIT_FREQUENCY;
+    let per_tick = PIT_DIVISOR * 1000;
+    return (clocks + per_tick - 1) / per_tick;
+}
+
+fn timer_interrupt_handler(_frame: &mut TrapFrame) {
+    let irq = IrqContext::enter();
+    let now = TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+    keyboard::tick();
+    crate::task::timer::tick(now);
+    crate::vga_buffer::status::update();
     unsafe {
         eoi(InterruptIndex::Timer as u8);
     }
+    // may switch threads, so the eoi has to be sent and the handler left already
+    drop(irq);
+    crate::thread::tick(now);
 }
\ No newline at end of file
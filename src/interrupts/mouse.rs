@@ -0,0 +1,288 @@
+use crate::sync::SpinLock;
+use crate::warn;
+use crate::vga_buffer::{CONSOLES, BUFFER_HEIGHT, BUFFER_WIDTH, FIRST_TEXT_ROW};
+use super::{eoi, ps2, unmask_irq, InterruptIndex};
+use super::trap::TrapFrame;
+
+use lazy_static::lazy_static;
+
+const MOUSE_IRQ: u8 = 12;
+const EVENT_QUEUE_SIZE: usize = 32;
+
+// mouse movement units needed to move the text cursor by one cell
+const MICKEYS_PER_COLUMN: i32 = 8;
+const MICKEYS_PER_ROW: i32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MouseButtons(u8);
+
+impl MouseButtons {
+    pub fn left(&self) -> bool {
+        return self.0 & 0x01 != 0;
+    }
+
+    pub fn right(&self) -> bool {
+        return self.0 & 0x02 != 0;
+    }
+
+    pub fn middle(&self) -> bool {
+        return self.0 & 0x04 != 0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MouseEvent {
+    pub dx: i16,
+    pub dy: i16,
+    pub buttons: MouseButtons,
+    pub wheel: i8,
+}
+
+pub struct PacketDecoder {
+    packet: [u8; 4],
+    index: usize,
+    packet_size: usize,
+}
+
+impl PacketDecoder {
+
+    pub const fn new() -> PacketDecoder {
+        return PacketDecoder {
+            packet: [0; 4],
+            index: 0,
+            packet_size: 3
+        };
+    }
+
+    pub const fn intellimouse() -> PacketDecoder {
+        return PacketDecoder {
+            packet: [0; 4],
+            index: 0,
+            packet_size: 4
+        };
+    }
+
+    pub fn push(&mut self, byte: u8) -> Option<MouseEvent> {
+        // bit 3 of the first byte is always set, use it to resynchronize
+        if self.index == 0 && byte & 0x08 == 0 {
+            return None;
+        }
+        self.packet[self.index] = byte;
+        self.index = self.index + 1;
+        if self.index < self.packet_size {
+            return None;
+        }
+        self.index = 0;
+        return Some(self.decode());
+    }
+
+    fn decode(&self) -> MouseEvent {
+        let flags = self.packet[0];
+        let mut dx = self.packet[1] as i16 - (((flags as i16) << 4) & 0x100);
+        let mut dy = self.packet[2] as i16 - (((flags as i16) << 3) & 0x100);
+        if flags & 0x40 != 0 {
+            dx = 0;
+        }
+        if flags & 0x80 != 0 {
+            dy = 0;
+        }
+        let wheel = match self.packet_size {
+            4 => ((self.packet[3] << 4) as i8) >> 4,
+            _ => 0
+        };
+        return MouseEvent {
+            dx,
+            dy,
+            buttons: MouseButtons(flags & 0x07),
+            wheel
+        };
+    }
+}
+
+struct Mouse {
+    decoder: PacketDecoder,
+    show_cursor: bool,
+    x: i32,
+    y: i32,
+    events: [MouseEvent; EVENT_QUEUE_SIZE],
+    head: usize,
+    len: usize,
+}
+
+lazy_static! {
+    static ref MOUSE: SpinLock<Mouse> = SpinLock::named("mouse", Mouse::new());
+}
+
+impl Mouse {
+
+    fn new() -> Mouse {
+        return Mouse {
+            decoder: PacketDecoder::new(),
+            show_cursor: false,
+            x: 0,
+            y: FIRST_TEXT_ROW as i32 * MICKEYS_PER_ROW,
+            events: [MouseEvent::default(); EVENT_QUEUE_SIZE],
+            head: 0,
+            len: 0
+        };
+    }
+
+    fn cell(&self) -> (usize, usize) {
+        return ((self.y / MICKEYS_PER_ROW) as usize, (self.x / MICKEYS_PER_COLUMN) as usize);
+    }
+
+    fn handle(&mut self, event: MouseEvent) {
+        let max_x = BUFFER_WIDTH as i32 * MICKEYS_PER_COLUMN - 1;
+        let min_y = FIRST_TEXT_ROW as i32 * MICKEYS_PER_ROW;
+        let max_y = BUFFER_HEIGHT as i32 * MICKEYS_PER_ROW - 1;
+        self.x = (self.x + event.dx as i32).max(0).min(max_x);
+        // the mouse reports y growing upwards, the screen grows downwards
+        self.y = (self.y - event.dy as i32).max(min_y).min(max_y);
+
+        if self.len == EVENT_QUEUE_SIZE {
+            self.head = (self.head + 1) % EVENT_QUEUE_SIZE;
+            self.len = self.len - 1;
+        }
+        self.events[(self.head + self.len) % EVENT_QUEUE_SIZE] = event;
+        self.len = self.len + 1;
+    }
+
+    fn pop(&mut self) -> Option<MouseEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.head];
+        self.head = (self.head + 1) % EVENT_QUEUE_SIZE;
+        self.len = self.len - 1;
+        return Some(event);
+    }
+}
+
+pub fn init() {
+    let intellimouse = x86_64::instructions::interrupts::without_interrupts(|| {
+        return configure();
+    });
+    match intellimouse {
+        Some(intellimouse) => {
+            if intellimouse {
+                x86_64::instructions::interrupts::without_interrupts(|| {
+                    MOUSE.lock().decoder = PacketDecoder::intellimouse();
+                });
+            }
+            unmask_irq(MOUSE_IRQ);
+        },
+        None => warn!("ps/2 mouse not responding, leaving it disabled")
+    }
+}
+
+// returns whether the device speaks the intellimouse 4-byte protocol
+fn configure() -> Option<bool> {
+    // enable the auxiliary port and its interrupt
+    if !ps2::command(0xa8) || !ps2::command(0x20) {
+        return None;
+    }
+    let config = (ps2::read_data()? | 0x02) & !0x20;
+    if !ps2::command(0x60) || !ps2::write_data(config) {
+        return None;
+    }
+
+    if ps2::write_aux(0xf6)? != ps2::ACK {
+        return None;
+    }
+
+    // magic sample rate sequence that switches intellimouse devices to id 3
+    for rate in [200, 100, 80].iter() {
+        ps2::write_aux(0xf3)?;
+        ps2::write_aux(*rate)?;
+    }
+    ps2::write_aux(0xf2)?;
+    let id = ps2::read_data()?;
+
+    if ps2::write_aux(0xf4)? != ps2::ACK {
+        return None;
+    }
+    return Some(id == 3);
+}
+
+pub fn show_cursor(show: bool) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut mouse = MOUSE.lock();
+        mouse.show_cursor = show;
+        let mut consoles = CONSOLES.lock();
+        let writer = consoles.active();
+        if show {
+            let (row, col) = mouse.cell();
+            writer.set_mouse_cursor(row, col);
+        }
+        else {
+            writer.hide_mouse_cursor();
+        }
+    });
+}
+
+pub fn next_event() -> Option<MouseEvent> {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        return MOUSE.lock().pop();
+    });
+}
+
+pub(crate) fn mouse_interrupt_handler(_frame: &mut TrapFrame) {
+    let _irq = super::IrqContext::enter();
+    use x86_64::instructions::port::Port;
+    let mut port = Port::new(0x60);
+    let byte: u8 = unsafe { port.read() };
+    let mut mouse = MOUSE.lock();
+    if let Some(event) = mouse.decoder.push(byte) {
+        mouse.handle(event);
+        if mouse.show_cursor {
+            let (row, col) = mouse.cell();
+            CONSOLES.lock().active().set_mouse_cursor(row, col);
+        }
+    }
+    unsafe {
+        eoi(InterruptIndex::Mouse as u8);
+    }
+}
+
+#[test_case]
+fn decode_standard_packet() {
+    let mut decoder = PacketDecoder::new();
+    assert_eq!(decoder.push(0x09), None);
+    assert_eq!(decoder.push(0x05), None);
+    let event = decoder.push(0x03).unwrap();
+    assert_eq!(event.dx, 5);
+    assert_eq!(event.dy, 3);
+    assert!(event.buttons.left());
+    assert!(!event.buttons.right());
+    assert_eq!(event.wheel, 0);
+}
+
+#[test_case]
+fn decode_negative_movement() {
+    let mut decoder = PacketDecoder::new();
+    decoder.push(0x38);
+    decoder.push(0xfe);
+    let event = decoder.push(0xfb).unwrap();
+    assert_eq!(event.dx, -2);
+    assert_eq!(event.dy, -5);
+}
+
+#[test_case]
+fn decode_intellimouse_wheel() {
+    let mut decoder = PacketDecoder::intellimouse();
+    decoder.push(0x0a);
+    decoder.push(0x00);
+    assert_eq!(decoder.push(0x00), None);
+    let event = decoder.push(0x0f).unwrap();
+    assert!(event.buttons.right());
+    assert_eq!(event.wheel, -1);
+}
+
+#[test_case]
+fn decoder_resynchronizes() {
+    let mut decoder = PacketDecoder::new();
+    assert_eq!(decoder.push(0x00), None);
+    decoder.push(0x08);
+    decoder.push(0x01);
+    assert_eq!(decoder.push(0x01).unwrap().dx, 1);
+}
@@ -0,0 +1,75 @@
+use x86_64::instructions::port::Port;
+
+const DATA_PORT: u16 = 0x60;
+const STATUS_PORT: u16 = 0x64;
+const COMMAND_PORT: u16 = 0x64;
+
+const OUTPUT_FULL: u8 = 0x01;
+const INPUT_FULL: u8 = 0x02;
+
+const TIMEOUT: usize = 100_000;
+
+pub const ACK: u8 = 0xfa;
+
+fn status() -> u8 {
+    let mut port: Port<u8> = Port::new(STATUS_PORT);
+    return unsafe { port.read() };
+}
+
+fn wait_write() -> bool {
+    for _ in 0..TIMEOUT {
+        if status() & INPUT_FULL == 0 {
+            return true;
+        }
+    }
+    return false;
+}
+
+fn wait_read() -> bool {
+    for _ in 0..TIMEOUT {
+        if status() & OUTPUT_FULL != 0 {
+            return true;
+        }
+    }
+    return false;
+}
+
+pub fn command(command: u8) -> bool {
+    if !wait_write() {
+        return false;
+    }
+    let mut port: Port<u8> = Port::new(COMMAND_PORT);
+    unsafe { port.write(command) };
+    return true;
+}
+
+pub fn write_data(byte: u8) -> bool {
+    if !wait_write() {
+        return false;
+    }
+    let mut port: Port<u8> = Port::new(DATA_PORT);
+    unsafe { port.write(byte) };
+    return true;
+}
+
+pub fn read_data() -> Option<u8> {
+    if !wait_read() {
+        return None;
+    }
+    let mut port: Port<u8> = Port::new(DATA_PORT);
+    return Some(unsafe { port.read() });
+}
+
+// sends a byte to the device on the auxiliary port and returns its response
+pub fn write_aux(byte: u8) -> Option<u8> {
+    if !command(0xd4) || !write_data(byte) {
+        return None;
+    }
+    return read_data();
+}
+
+// pulses the cpu reset line through the controller output port
+pub fn reset() -> ! {
+    command(0xfe);
+    crate::halt();
+}
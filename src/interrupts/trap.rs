@@ -0,0 +1,187 @@
+// entry stubs for every vector the kernel handles. they swap in the kernel gs base
+// when coming from ring 3 and save every general purpose register, so a debugger
+// can inspect and change them before returning
+
+use x86_64::structures::idt::{DivergingHandlerFuncWithErrCode, HandlerFunc, PageFaultHandlerFunc};
+
+use super::apic;
+use super::InterruptIndex;
+
+pub const DEBUG_VECTOR: u64 = 1;
+pub const BREAKPOINT_VECTOR: u64 = 3;
+pub const DOUBLE_FAULT_VECTOR: u64 = 8;
+pub const PAGE_FAULT_VECTOR: u64 = 14;
+
+const TIMER_VECTOR: u64 = InterruptIndex::Timer as u64;
+const KEYBOARD_VECTOR: u64 = InterruptIndex::Keyboard as u64;
+const COM2_VECTOR: u64 = InterruptIndex::Com2 as u64;
+const COM1_VECTOR: u64 = InterruptIndex::Com1 as u64;
+const MOUSE_VECTOR: u64 = InterruptIndex::Mouse as u64;
+const CALL_FUNCTION_VECTOR: u64 = apic::CALL_FUNCTION_VECTOR as u64;
+const SPURIOUS_VECTOR: u64 = apic::SPURIOUS_VECTOR as u64;
+
+pub const TRAP_FLAG: u64 = 1 << 8;
+
+// the layout pushed by `trap_common` followed by what the cpu pushed
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct TrapFrame {
+    pub rax: u64,
+    pub rbx: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub rbp: u64,
+    pub r8: u64,
+    pub r9: u64,
+    pub r10: u64,
+    pub r11: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+    pub vector: u64,
+    // zero for vectors where the cpu doesn't push one
+    pub error_code: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+}
+
+// the vector numbers have to match the constants above
+global_asm!(r#"
+.macro trap_entry name, vector
+.global \name
+\name:
+    pushq $0
+    pushq $\vector
+    jmp trap_common
+.endm
+
+// the cpu pushed an error code already
+.macro trap_entry_error_code name, vector
+.global \name
+\name:
+    pushq $\vector
+    jmp trap_common
+.endm
+
+trap_entry debug_trap_entry, 1
+trap_entry breakpoint_trap_entry, 3
+trap_entry_error_code double_fault_trap_entry, 8
+trap_entry_error_code page_fault_trap_entry, 14
+trap_entry timer_trap_entry, 32
+trap_entry keyboard_trap_entry, 33
+trap_entry com2_trap_entry, 35
+trap_entry com1_trap_entry, 36
+trap_entry mouse_trap_entry, 44
+trap_entry call_function_trap_entry, 0xf0
+trap_entry spurious_trap_entry, 0xff
+
+trap_common:
+    // gs holds the per-cpu block in the kernel and the user's base in ring 3
+    testb $3, 24(%rsp)
+    jz 1f
+    swapgs
+1:
+    pushq %r15
+    pushq %r14
+    pushq %r13
+    pushq %r12
+    pushq %r11
+    pushq %r10
+    pushq %r9
+    pushq %r8
+    pushq %rbp
+    pushq %rdi
+    pushq %rsi
+    pushq %rdx
+    pushq %rcx
+    pushq %rbx
+    pushq %rax
+    movq %rsp, %rdi
+    // the cpu frame, error code, vector and 15 registers keep the 16 byte alignment
+    cld
+    call trap_dispatch
+    popq %rax
+    popq %rbx
+    popq %rcx
+    popq %rdx
+    popq %rsi
+    popq %rdi
+    popq %rbp
+    popq %r8
+    popq %r9
+    popq %r10
+    popq %r11
+    popq %r12
+    popq %r13
+    popq %r14
+    popq %r15
+    addq $16, %rsp
+    testb $3, 8(%rsp)
+    jz 2f
+    swapgs
+2:
+    iretq
+"#);
+
+extern "C" {
+    fn debug_trap_entry();
+    fn breakpoint_trap_entry();
+    fn double_fault_trap_entry();
+    fn page_fault_trap_entry();
+    fn timer_trap_entry();
+    fn keyboard_trap_entry();
+    fn com2_trap_entry();
+    fn com1_trap_entry();
+    fn mouse_trap_entry();
+    fn call_function_trap_entry();
+    fn spurious_trap_entry();
+}
+
+#[no_mangle]
+extern "C" fn trap_dispatch(frame: &mut TrapFrame) {
+    match frame.vector {
+        DEBUG_VECTOR => super::debug_handler(frame),
+        BREAKPOINT_VECTOR => super::breakpoint_handler(frame),
+        DOUBLE_FAULT_VECTOR => super::double_fault_handler(frame),
+        PAGE_FAULT_VECTOR => super::page_fault_handler(frame),
+        TIMER_VECTOR => super::timer_interrupt_handler(frame),
+        KEYBOARD_VECTOR => super::keyboard::keyboard_interrupt_handler(frame),
+        COM2_VECTOR => crate::serial::com2_interrupt_handler(frame),
+        COM1_VECTOR => crate::serial::com1_interrupt_handler(frame),
+        MOUSE_VECTOR => super::mouse::mouse_interrupt_handler(frame),
+        CALL_FUNCTION_VECTOR => crate::smp::call::call_function_interrupt_handler(frame),
+        SPURIOUS_VECTOR => super::spurious_interrupt_handler(frame),
+        _ => panic!("no handler for vector {}\n{:#?}", frame.vector, frame)
+    }
+}
+
+// the stubs follow the interrupt calling convention, they just aren't rust functions
+pub fn entry(vector: u64) -> HandlerFunc {
+    let stub: unsafe extern "C" fn() = match vector {
+        DEBUG_VECTOR => debug_trap_entry,
+        BREAKPOINT_VECTOR => breakpoint_trap_entry,
+        TIMER_VECTOR => timer_trap_entry,
+        KEYBOARD_VECTOR => keyboard_trap_entry,
+        COM2_VECTOR => com2_trap_entry,
+        COM1_VECTOR => com1_trap_entry,
+        MOUSE_VECTOR => mouse_trap_entry,
+        CALL_FUNCTION_VECTOR => call_function_trap_entry,
+        SPURIOUS_VECTOR => spurious_trap_entry,
+        _ => panic!("no entry stub for vector {}", vector)
+    };
+    return unsafe { core::mem::transmute(stub) };
+}
+
+pub fn double_fault_entry() -> DivergingHandlerFuncWithErrCode {
+    return unsafe { core::mem::transmute(double_fault_trap_entry as unsafe extern "C" fn()) };
+}
+
+pub fn page_fault_entry() -> PageFaultHandlerFunc {
+    return unsafe { core::mem::transmute(page_fault_trap_entry as unsafe extern "C" fn()) };
+}
@@ -2,22 +2,38 @@
 
 #![cfg_attr(test, no_main)]
 #![feature(custom_test_frameworks)]
-#![feature(abi_x86_interrupt)]
 #![feature(or_patterns)]
 #![feature(alloc_error_handler)]
+#![feature(global_asm)]
+#![feature(asm)]
 #![test_runner(crate::test_runner)]
 #![reexport_test_harness_main = "test_main"]
 
 use core::panic::PanicInfo;
+#[cfg(test)]
+use bootloader::{BootInfo, entry_point};
 
 extern crate rlibc;
 extern crate alloc;
 
 pub mod vga_buffer;
 pub mod serial;
+pub mod console;
+pub mod log;
 pub mod interrupts;
 pub mod gdt;
+pub mod usermode;
+pub mod syscall;
+pub mod task;
+pub mod thread;
+pub mod sync;
 pub mod memory;
+pub mod acpi;
+pub mod smp;
+pub mod pci;
+pub mod framebuffer;
+pub mod graphics;
+pub mod gdb;
 
 pub fn test_runner(tests: &[&dyn Testable]) {
     serial_println!("Running {} tests", tests.len());
@@ -50,16 +66,28 @@ pub fn test_panic_handler(info: &PanicInfo) -> ! {
 }
 
 pub fn init() {
+    vga_buffer::cursor::enable(vga_buffer::cursor::CursorShape::Underline);
+    vga_buffer::console::init();
+    vga_buffer::status::update();
     gdt::init();
+    syscall::init();
     interrupts::init_idt();
     unsafe { interrupts::PICS.lock().initialize() };
+    interrupts::mouse::init();
+    interrupts::keyboard::init();
+    serial::init();
     x86_64::instructions::interrupts::enable();
 }
 
 #[cfg(test)]
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
+entry_point!(test_kernel_main);
+
+#[cfg(test)]
+fn test_kernel_main(boot_info: &'static BootInfo) -> ! {
     init();
+    memory::init(boot_info);
+    thread::init();
+    vga_buffer::console::init_scrollback(vga_buffer::scrollback::DEFAULT_SCROLLBACK_LINES);
     test_main();
     halt();
 }
@@ -89,6 +117,7 @@ pub enum QemuExitCode {
 
 pub fn exit_qemu(exit_code: QemuExitCode) {
     use x86_64::instructions::port::Port;
+    serial::flush();
     let mut port = Port::new(0xf4);
     unsafe { port.write(exit_code as u32); }
 }
\ No newline at end of file
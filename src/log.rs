@@ -0,0 +1,281 @@
+use core::fmt::{self, Arguments, Write};
+
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::serial::{self, Channel};
+
+pub const DMESG_ENTRIES: usize = 256;
+pub const MESSAGE_SIZE: usize = 120;
+const MAX_FILTERS: usize = 16;
+
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => ($crate::log::_log($level, module_path!(), format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => ($crate::log!($crate::log::Level::Error, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => ($crate::log!($crate::log::Level::Warn, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => ($crate::log!($crate::log::Level::Info, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => ($crate::log!($crate::log::Level::Debug, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => ($crate::log!($crate::log::Level::Trace, $($arg)*));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE"
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogError {
+    TableFull,
+}
+
+// the most verbose level shown per module path prefix, the longest match wins
+struct Filters {
+    default: Level,
+    modules: [Option<(&'static str, Level)>; MAX_FILTERS],
+}
+
+impl Filters {
+
+    const fn new(default: Level) -> Filters {
+        return Filters {
+            default,
+            modules: [None; MAX_FILTERS]
+        };
+    }
+
+    fn set(&mut self, module: &'static str, level: Level) -> Result<(), LogError> {
+        let slot = self.modules.iter().position(|filter| filter.map_or(false, |(path, _)| path == module))
+            .or_else(|| self.modules.iter().position(|filter| filter.is_none()))
+            .ok_or(LogError::TableFull)?;
+        self.modules[slot] = Some((module, level));
+        return Ok(());
+    }
+
+    fn level(&self, module: &str) -> Level {
+        let mut best: Option<(&str, Level)> = None;
+        for filter in self.modules.iter().flatten() {
+            let (path, level) = *filter;
+            let matches = module.starts_with(path)
+                && (module.len() == path.len() || module[path.len()..].starts_with("::"));
+            if matches && best.map_or(true, |(best, _)| path.len() > best.len()) {
+                best = Some((path, level));
+            }
+        }
+        return best.map_or(self.default, |(_, level)| level);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Record {
+    pub timestamp_ms: u64,
+    pub level: Level,
+    pub module: &'static str,
+    text: [u8; MESSAGE_SIZE],
+    len: usize,
+}
+
+impl Record {
+    const EMPTY: Record = Record {
+        timestamp_ms: 0,
+        level: Level::Trace,
+        module: "",
+        text: [0; MESSAGE_SIZE],
+        len: 0
+    };
+
+    pub fn message(&self) -> &str {
+        return core::str::from_utf8(&self.text[..self.len]).unwrap_or("");
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (secs, millis) = (self.timestamp_ms / 1000, self.timestamp_ms % 1000);
+        return write!(f, "[{:5}.{:03}] {:5} {}: {}", secs, millis, self.level.name(), self.module, self.message());
+    }
+}
+
+// long messages are cut at a character boundary
+impl Write for Record {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let end = self.len + c.len_utf8();
+            if end > MESSAGE_SIZE {
+                break;
+            }
+            c.encode_utf8(&mut self.text[self.len..end]);
+            self.len = end;
+        }
+        return Ok(());
+    }
+}
+
+// keeps the most recent records, overwriting the oldest
+struct Dmesg {
+    records: [Record; DMESG_ENTRIES],
+    head: usize,
+    len: usize,
+}
+
+impl Dmesg {
+
+    const fn new() -> Dmesg {
+        return Dmesg {
+            records: [Record::EMPTY; DMESG_ENTRIES],
+            head: 0,
+            len: 0
+        };
+    }
+
+    fn push(&mut self, record: Record) {
+        if self.len == DMESG_ENTRIES {
+            self.head = (self.head + 1) % DMESG_ENTRIES;
+            self.len = self.len - 1;
+        }
+        self.records[(self.head + self.len) % DMESG_ENTRIES] = record;
+        self.len = self.len + 1;
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Record> {
+        return (0..self.len).map(move |i| &self.records[(self.head + i) % DMESG_ENTRIES]);
+    }
+}
+
+static FILTERS: Mutex<Filters> = Mutex::new(Filters::new(Level::Info));
+static DMESG: Mutex<Dmesg> = Mutex::new(Dmesg::new());
+
+pub fn set_level(level: Level) {
+    interrupts::without_interrupts(|| {
+        FILTERS.lock().default = level;
+    });
+}
+
+// `module` is a path like "ros::interrupts", it also covers the modules below it
+pub fn set_module_level(module: &'static str, level: Level) -> Result<(), LogError> {
+    return interrupts::without_interrupts(|| {
+        return FILTERS.lock().set(module, level);
+    });
+}
+
+pub fn enabled(level: Level, module: &str) -> bool {
+    return interrupts::without_interrupts(|| {
+        return level <= FILTERS.lock().level(module);
+    });
+}
+
+pub fn _log(level: Level, module: &'static str, args: Arguments) {
+    if !enabled(level, module) {
+        return;
+    }
+    let mut record = Record {
+        timestamp_ms: crate::interrupts::uptime_ms(),
+        level,
+        module,
+        ..Record::EMPTY
+    };
+    record.write_fmt(args).unwrap();
+
+    interrupts::without_interrupts(|| {
+        DMESG.lock().push(record);
+    });
+    serial::_channel_print(Channel::KernelLog, format_args!("{}\r\n", record));
+    // problems also show up on screen, the serial mirror already has them
+    if level <= Level::Warn {
+        crate::vga_buffer::_print(format_args!("{}\n", record));
+    }
+}
+
+// writes every retained record, oldest first
+pub fn dmesg(out: &mut impl Write) -> fmt::Result {
+    return interrupts::without_interrupts(|| {
+        for record in DMESG.lock().iter() {
+            writeln!(out, "{}", record)?;
+        }
+        return Ok(());
+    });
+}
+
+// for the panic handler, gives up instead of waiting if the ring is locked
+pub fn dump_to_serial() {
+    match DMESG.try_lock() {
+        Some(dmesg) => {
+            for record in dmesg.iter() {
+                serial::_channel_print(Channel::KernelLog, format_args!("{}\r\n", record));
+            }
+        },
+        None => serial::_channel_print(Channel::KernelLog, format_args!("dmesg is locked\r\n"))
+    }
+}
+
+#[test_case]
+fn test_module_filters() {
+    let mut filters = Filters::new(Level::Info);
+    filters.set("ros::interrupts", Level::Debug).unwrap();
+    filters.set("ros::interrupts::mouse", Level::Error).unwrap();
+    assert_eq!(filters.level("ros::memory"), Level::Info);
+    assert_eq!(filters.level("ros::interrupts"), Level::Debug);
+    assert_eq!(filters.level("ros::interrupts::keyboard"), Level::Debug);
+    assert_eq!(filters.level("ros::interrupts::mouse"), Level::Error);
+    assert_eq!(filters.level("ros::interruptsx"), Level::Info);
+}
+
+#[test_case]
+fn test_dmesg_keeps_recent_records() {
+    // a buffer of its own, the global one holds the boot log
+    let mut dmesg = Dmesg::new();
+    for i in 0..DMESG_ENTRIES + 3 {
+        let mut record = Record::EMPTY;
+        write!(record, "{}", i).unwrap();
+        dmesg.push(record);
+    }
+    let mut records = dmesg.iter();
+    assert_eq!(records.next().unwrap().message(), "3");
+    assert_eq!(records.last().unwrap().message(), "258");
+}
+
+#[test_case]
+fn test_record_truncates_long_messages() {
+    let mut record = Record::EMPTY;
+    for _ in 0..MESSAGE_SIZE {
+        record.write_str("é").unwrap();
+    }
+    assert_eq!(record.message().len(), MESSAGE_SIZE);
+    assert!(record.message().ends_with('é'));
+}
@@ -5,33 +5,58 @@
 #![reexport_test_harness_main = "test_main"]
 
 use core::panic::PanicInfo;
-use bootloader::BootInfo;
-use x86_64::{
-    VirtAddr,
-    structures::paging::MapperAllSizes
-};
+use bootloader::{BootInfo, entry_point};
 
 extern crate alloc;
 use alloc::boxed::Box;
 
-use ros::memory::paging;
 use ros::println;
+use ros::task::{executor::Executor, keyboard, Task};
 
-#[no_mangle]
-pub extern "C" fn _start(boot_info: &'static BootInfo) -> ! {
+entry_point!(kernel_main);
+
+fn kernel_main(boot_info: &'static BootInfo) -> ! {
     #[cfg(test)]
     test_main();
 
     ros::init();
+    ros::memory::init(boot_info);
+    ros::thread::init();
+    ros::info!("scheduling threads with {}", ros::thread::policy_name());
+    match ros::smp::init() {
+        Ok(cpus) => ros::info!("{} cpus online", cpus),
+        Err(error) => ros::warn!("running on the bootstrap processor only: {:?}", error)
+    }
+    ros::vga_buffer::console::init_scrollback(ros::vga_buffer::scrollback::DEFAULT_SCROLLBACK_LINES);
+    ros::interrupts::mouse::show_cursor(true);
+    #[cfg(feature = "gdb")]
+    {
+        match ros::gdb::init() {
+            Ok(()) => {
+                println!("waiting for gdb on the debug serial port");
+                ros::gdb::breakpoint();
+            },
+            Err(error) => println!("gdb stub disabled: {:?}", error)
+        }
+    }
+    #[cfg(feature = "framebuffer")]
+    {
+        if let Err(error) = ros::framebuffer::init(1024, 768) {
+            println!("staying in text mode: {:?}", error);
+        }
+    }
     let x = Box::new(41);
     println!("hello human");
 
-    ros::halt();
+    let mut executor = Executor::new();
+    executor.spawn(Task::new(keyboard::trace_scancodes()));
+    executor.run();
 }
 
 #[cfg(not(test))]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    ros::log::dump_to_serial();
     println!("{}", info);
     ros::halt();
 }
@@ -40,4 +65,4 @@ fn panic(info: &PanicInfo) -> ! {
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     ros::test_panic_handler(info);
-}
\ No newline at end of file
+}
@@ -1,16 +1,89 @@
-use alloc::alloc::{GlobalAlloc, Layout};
-use core::ptr::null_mut;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{self, NonNull};
+
+use x86_64::{
+    VirtAddr,
+    structures::paging::{
+        mapper::MapToError,
+        FrameAllocator,
+        Mapper,
+        Page,
+        PageTableFlags,
+        Size4KiB
+    }
+};
+use linked_list_allocator::Heap;
+
+use crate::sync::SpinLock;
+
+pub const HEAP_START: usize = 0x_4444_4444_0000;
+pub const HEAP_SIZE: usize = 4 * 1024 * 1024;
 
 #[global_allocator]
-static ALLOCATOR: Dummy = Dummy;
+static ALLOCATOR: Allocator = Allocator {
+    heap: SpinLock::named("heap", Heap::empty())
+};
+
+// the heap behind a lock lockdep tracks, interrupt handlers must not allocate
+struct Allocator {
+    heap: SpinLock<Heap>,
+}
+
+unsafe impl GlobalAlloc for Allocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        return match self.heap.lock().allocate_first_fit(layout) {
+            Ok(allocation) => allocation.as_ptr(),
+            Err(()) => ptr::null_mut()
+        };
+    }
 
-pub struct Dummy;
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap.lock().deallocate(NonNull::new_unchecked(ptr), layout);
+    }
+}
+
+pub fn init_heap(
+    mapper: &mut impl Mapper<Size4KiB>,
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>
+) -> Result<(), MapToError<Size4KiB>> {
+    let page_range = {
+        let heap_start = VirtAddr::new(HEAP_START as u64);
+        let heap_end = heap_start + HEAP_SIZE - 1u64;
+        let heap_start_page = Page::containing_address(heap_start);
+        let heap_end_page = Page::containing_address(heap_end);
+        Page::range_inclusive(heap_start_page, heap_end_page)
+    };
 
-unsafe impl GlobalAlloc for Dummy {
-    unsafe fn alloc(&self, _layout: Layout) -> *mut u8 {
-        return null_mut();
+    for page in page_range {
+        let frame = frame_allocator
+            .allocate_frame()
+            .ok_or(MapToError::FrameAllocationFailed)?;
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        unsafe {
+            mapper.map_to(page, frame, flags, frame_allocator)?.flush();
+        }
     }
-    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {
-        panic!("no dealloc bitch");
+
+    unsafe {
+        ALLOCATOR.heap.lock().init(HEAP_START, HEAP_SIZE);
     }
-}
\ No newline at end of file
+    return Ok(());
+}
+
+pub fn used() -> usize {
+    return ALLOCATOR.heap.lock().used();
+}
+
+pub fn free() -> usize {
+    return ALLOCATOR.heap.lock().free();
+}
+
+// for interrupt handlers, which must not wait on the heap lock
+pub fn try_free() -> Option<usize> {
+    return ALLOCATOR.heap.try_lock().map(|heap| heap.free());
+}
+
+// used and free bytes, or None while the heap is locked
+pub fn try_usage() -> Option<(usize, usize)> {
+    return ALLOCATOR.heap.try_lock().map(|heap| (heap.used(), heap.free()));
+}
@@ -1,2 +1,120 @@
 pub mod paging;
-pub mod allocator;
\ No newline at end of file
+pub mod allocator;
+
+use bootloader::BootInfo;
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Mutex;
+use x86_64::{PhysAddr, VirtAddr};
+use x86_64::structures::paging::{FrameAllocator, OffsetPageTable, Page, PageRangeInclusive, PageTableFlags, PhysFrame, Size4KiB};
+use x86_64::structures::paging::mapper::{FlagUpdateError, MapToError, UnmapError};
+
+use paging::BootInfoFrameAllocator;
+
+pub static MAPPER: Mutex<Option<OffsetPageTable<'static>>> = Mutex::new(None);
+pub static FRAME_ALLOCATOR: Mutex<Option<BootInfoFrameAllocator>> = Mutex::new(None);
+static PHYSICAL_MEMORY_OFFSET: AtomicU64 = AtomicU64::new(0);
+// a frame below 1 MiB set aside for the application processor trampoline
+static LOW_FRAME: Mutex<Option<PhysFrame>> = Mutex::new(None);
+
+pub fn init(boot_info: &'static BootInfo) {
+    let physical_memory_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    PHYSICAL_MEMORY_OFFSET.store(boot_info.physical_memory_offset, Ordering::Relaxed);
+    let mut mapper = unsafe { paging::init(physical_memory_offset) };
+    let mut frame_allocator = BootInfoFrameAllocator::init(&boot_info.memory_map);
+
+    // frames are handed out from the bottom, so the first one is the lowest
+    let low_frame = frame_allocator.allocate_frame().filter(|frame| frame.start_address().as_u64() < 0x10_0000);
+    *LOW_FRAME.lock() = low_frame;
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    *MAPPER.lock() = Some(mapper);
+    *FRAME_ALLOCATOR.lock() = Some(frame_allocator);
+}
+
+pub fn map_physical_region(
+    physical_start: PhysAddr,
+    size: u64,
+    virtual_start: VirtAddr,
+    flags: PageTableFlags
+) -> Result<(), MapToError<Size4KiB>> {
+    let mut mapper = MAPPER.lock();
+    let mut frame_allocator = FRAME_ALLOCATOR.lock();
+    let mapper = mapper.as_mut().expect("memory not initialized");
+    let frame_allocator = frame_allocator.as_mut().expect("memory not initialized");
+    return paging::map_physical_region(mapper, frame_allocator, physical_start, size, virtual_start, flags);
+}
+
+pub fn map_user_region(virtual_start: VirtAddr, size: u64) -> Result<(), MapToError<Size4KiB>> {
+    let physical_memory_offset = VirtAddr::new(PHYSICAL_MEMORY_OFFSET.load(Ordering::Relaxed));
+    let mut mapper = MAPPER.lock();
+    let mut frame_allocator = FRAME_ALLOCATOR.lock();
+    let mapper = mapper.as_mut().expect("memory not initialized");
+    let frame_allocator = frame_allocator.as_mut().expect("memory not initialized");
+    return paging::map_user_region(mapper, frame_allocator, physical_memory_offset, virtual_start, size);
+}
+
+// the frames are not handed back, the frame allocator can't take them
+pub fn unmap_region(virtual_start: VirtAddr, size: u64) -> Result<(), UnmapError> {
+    if size == 0 {
+        return Ok(());
+    }
+    let pages = page_range(virtual_start, size);
+    let result = {
+        let mut mapper = MAPPER.lock();
+        let mapper = mapper.as_mut().expect("memory not initialized");
+        paging::unmap_region(mapper, pages)
+    };
+    // without the page table lock, the other cpus may be waiting for it with interrupts off
+    crate::smp::tlb::shootdown(pages);
+    return result;
+}
+
+pub fn protect_region(virtual_start: VirtAddr, size: u64, flags: PageTableFlags) -> Result<(), FlagUpdateError> {
+    if size == 0 {
+        return Ok(());
+    }
+    let pages = page_range(virtual_start, size);
+    let result = {
+        let mut mapper = MAPPER.lock();
+        let mapper = mapper.as_mut().expect("memory not initialized");
+        paging::protect_region(mapper, pages, flags)
+    };
+    crate::smp::tlb::shootdown(pages);
+    return result;
+}
+
+fn page_range(virtual_start: VirtAddr, size: u64) -> PageRangeInclusive {
+    return Page::range_inclusive(
+        Page::containing_address(virtual_start),
+        Page::containing_address(virtual_start + size - 1u64)
+    );
+}
+
+// whether ring 3 may touch every byte of [virtual_start, virtual_start + size)
+pub fn is_user_accessible(virtual_start: VirtAddr, size: u64) -> bool {
+    if size == 0 {
+        return true;
+    }
+    let physical_memory_offset = VirtAddr::new(PHYSICAL_MEMORY_OFFSET.load(Ordering::Relaxed));
+    let mut mapper = MAPPER.lock();
+    let mapper = match mapper.as_mut() {
+        Some(mapper) => mapper,
+        None => return false
+    };
+    for page in page_range(virtual_start, size) {
+        if !paging::is_user_accessible(mapper, physical_memory_offset, page) {
+            return false;
+        }
+    }
+    return true;
+}
+
+// everything physical is mapped at the offset the bootloader picked
+pub fn phys_to_virt(physical: PhysAddr) -> VirtAddr {
+    return VirtAddr::new(PHYSICAL_MEMORY_OFFSET.load(Ordering::Relaxed) + physical.as_u64());
+}
+
+pub fn low_frame() -> Option<PhysFrame> {
+    return *LOW_FRAME.lock();
+}
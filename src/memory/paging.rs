@@ -2,9 +2,14 @@ use x86_64::{
     VirtAddr,
     PhysAddr,
     structures::paging::{
+        mapper::{FlagUpdateError, MapToError, UnmapError},
         PageTable,
+        PageRangeInclusive,
         OffsetPageTable,
+        Page,
         PhysFrame,
+        PageTableFlags,
+        Mapper,
         Size4KiB,
         FrameAllocator
     },
@@ -15,15 +20,26 @@ use bootloader::bootinfo::{MemoryMap,MemoryRegionType};
 pub struct BootInfoFrameAllocator {
     memory_map: &'static MemoryMap,
     next: usize,
+    total: usize,
 }
 
 impl BootInfoFrameAllocator {
     pub fn init(memory_map: &'static MemoryMap) -> Self {
+        let total = memory_map.iter()
+            .filter(|r| r.region_type == MemoryRegionType::Usable)
+            .map(|r| ((r.range.end_addr() - r.range.start_addr()) / 4096) as usize)
+            .sum();
         BootInfoFrameAllocator {
             memory_map,
             next: 0,
+            total,
         }
     }
+
+    pub fn free_frames(&self) -> usize {
+        return self.total.saturating_sub(self.next);
+    }
+
     fn usable_frames(&self) -> impl Iterator<Item = PhysFrame> {
         let regions = self.memory_map.iter();
         let usable_regions = regions
@@ -58,4 +74,98 @@ unsafe fn active_level_4_table(physical_memory_offset: VirtAddr) -> &'static mut
     let page_table_ptr: *mut PageTable = virtual_addr.as_mut_ptr();
 
     return &mut *page_table_ptr;
-}
\ No newline at end of file
+}
+pub fn map_physical_region(
+    mapper: &mut impl Mapper<Size4KiB>,
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
+    physical_start: PhysAddr,
+    size: u64,
+    virtual_start: VirtAddr,
+    flags: PageTableFlags
+) -> Result<(), MapToError<Size4KiB>> {
+    let frames = PhysFrame::range_inclusive(
+        PhysFrame::containing_address(physical_start),
+        PhysFrame::containing_address(physical_start + size - 1u64)
+    );
+    for (i, frame) in frames.enumerate() {
+        let page = Page::containing_address(virtual_start + i as u64 * 4096);
+        unsafe {
+            mapper.map_to(page, frame, flags, frame_allocator)?.flush();
+        }
+    }
+    return Ok(());
+}
+
+// flushes this cpu's tlb only, see `smp::tlb::shootdown` for the others
+pub fn unmap_region(mapper: &mut impl Mapper<Size4KiB>, pages: PageRangeInclusive) -> Result<(), UnmapError> {
+    for page in pages {
+        let (_, flush) = mapper.unmap(page)?;
+        flush.flush();
+    }
+    return Ok(());
+}
+
+pub fn protect_region(
+    mapper: &mut impl Mapper<Size4KiB>,
+    pages: PageRangeInclusive,
+    flags: PageTableFlags
+) -> Result<(), FlagUpdateError> {
+    for page in pages {
+        unsafe {
+            mapper.update_flags(page, flags)?.flush();
+        }
+    }
+    return Ok(());
+}
+
+// backs `size` bytes at `virtual_start` with fresh frames that ring 3 can use
+pub fn map_user_region(
+    mapper: &mut OffsetPageTable,
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
+    physical_memory_offset: VirtAddr,
+    virtual_start: VirtAddr,
+    size: u64
+) -> Result<(), MapToError<Size4KiB>> {
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
+    let pages = Page::range_inclusive(
+        Page::<Size4KiB>::containing_address(virtual_start),
+        Page::containing_address(virtual_start + size - 1u64)
+    );
+    for page in pages {
+        let frame = frame_allocator.allocate_frame().ok_or(MapToError::FrameAllocationFailed)?;
+        unsafe {
+            mapper.map_to(page, frame, flags, frame_allocator)?.flush();
+        }
+        set_user_accessible(mapper, physical_memory_offset, page);
+    }
+    return Ok(());
+}
+
+// map_to creates the intermediate tables supervisor-only, which still faults in ring 3
+fn set_user_accessible(mapper: &mut OffsetPageTable, physical_memory_offset: VirtAddr, page: Page) {
+    let mut table: *mut PageTable = mapper.level_4_table();
+    for index in [page.p4_index(), page.p3_index(), page.p2_index()].iter() {
+        let entry = unsafe { &mut (*table)[*index] };
+        entry.set_flags(entry.flags() | PageTableFlags::USER_ACCESSIBLE);
+        table = (physical_memory_offset + entry.addr().as_u64()).as_mut_ptr();
+    }
+    x86_64::instructions::tlb::flush(page.start_address());
+}
+
+// walks the tables by hand, translate doesn't report the flags of every level
+pub fn is_user_accessible(mapper: &mut OffsetPageTable, physical_memory_offset: VirtAddr, page: Page) -> bool {
+    let mut table: *const PageTable = mapper.level_4_table();
+    let indices = [page.p4_index(), page.p3_index(), page.p2_index(), page.p1_index()];
+    for index in indices.iter() {
+        let entry = unsafe { &(*table)[*index] };
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE) {
+            return false;
+        }
+        if flags.contains(PageTableFlags::HUGE_PAGE) {
+            return true;
+        }
+        table = (physical_memory_offset + entry.addr().as_u64()).as_ptr();
+    }
+    return true;
+}
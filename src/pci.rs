@@ -0,0 +1,53 @@
+use x86_64::instructions::port::Port;
+
+const CONFIG_ADDRESS: u16 = 0xcf8;
+const CONFIG_DATA: u16 = 0xcfc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciDevice {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+    pub vendor_id: u16,
+    pub device_id: u16,
+}
+
+pub fn read_config(bus: u8, device: u8, function: u8, offset: u8) -> u32 {
+    let address = 0x8000_0000
+        | (bus as u32) << 16
+        | (device as u32) << 11
+        | (function as u32) << 8
+        | (offset as u32 & 0xfc);
+    let mut address_port: Port<u32> = Port::new(CONFIG_ADDRESS);
+    let mut data_port: Port<u32> = Port::new(CONFIG_DATA);
+    unsafe {
+        address_port.write(address);
+        return data_port.read();
+    }
+}
+
+impl PciDevice {
+
+    pub fn read(&self, offset: u8) -> u32 {
+        return read_config(self.bus, self.device, self.function, offset);
+    }
+
+    // memory bars have the address in the upper bits, the low 4 bits are flags
+    pub fn bar(&self, index: u8) -> u32 {
+        return self.read(0x10 + index * 4);
+    }
+}
+
+pub fn find(vendor_id: u16, device_id: u16) -> Option<PciDevice> {
+    for bus in 0..=255u8 {
+        for device in 0..32u8 {
+            for function in 0..8u8 {
+                let id = read_config(bus, device, function, 0);
+                if id as u16 == vendor_id && (id >> 16) as u16 == device_id {
+                    return Some(PciDevice { bus, device, function, vendor_id, device_id });
+                }
+            }
+        }
+    }
+    return None;
+}
@@ -0,0 +1,110 @@
+// the uart divides this clock by a 16 bit divisor to get the baud rate
+pub const UART_CLOCK: u32 = 115_200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    Mark,
+    Space,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialConfig {
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialError {
+    NotPresent,
+    InvalidBaudRate,
+}
+
+impl Default for SerialConfig {
+    // 38400 8N1, what uart_16550 used to set up
+    fn default() -> SerialConfig {
+        return SerialConfig {
+            baud_rate: 38400,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One
+        };
+    }
+}
+
+impl SerialConfig {
+
+    pub fn divisor(&self) -> Result<u16, SerialError> {
+        if self.baud_rate == 0 || UART_CLOCK % self.baud_rate != 0 {
+            return Err(SerialError::InvalidBaudRate);
+        }
+        let divisor = UART_CLOCK / self.baud_rate;
+        // the divisor latch is 16 bits wide
+        if divisor > u16::max_value() as u32 {
+            return Err(SerialError::InvalidBaudRate);
+        }
+        return Ok(divisor as u16);
+    }
+
+    // value for the line control register, without the DLAB bit
+    pub fn line_control(&self) -> u8 {
+        let data_bits = match self.data_bits {
+            DataBits::Five => 0x00,
+            DataBits::Six => 0x01,
+            DataBits::Seven => 0x02,
+            DataBits::Eight => 0x03
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 0x00,
+            StopBits::Two => 0x04
+        };
+        let parity = match self.parity {
+            Parity::None => 0x00,
+            Parity::Odd => 0x08,
+            Parity::Even => 0x18,
+            Parity::Mark => 0x28,
+            Parity::Space => 0x38
+        };
+        return data_bits | stop_bits | parity;
+    }
+}
+
+#[test_case]
+fn test_serial_config_registers() {
+    let config = SerialConfig::default();
+    assert_eq!(config.divisor(), Ok(3));
+    assert_eq!(config.line_control(), 0x03);
+
+    let config = SerialConfig {
+        baud_rate: 9600,
+        data_bits: DataBits::Seven,
+        parity: Parity::Even,
+        stop_bits: StopBits::Two
+    };
+    assert_eq!(config.divisor(), Ok(12));
+    assert_eq!(config.line_control(), 0x1e);
+
+    let config = SerialConfig { baud_rate: 1000, ..SerialConfig::default() };
+    assert_eq!(config.divisor(), Err(SerialError::InvalidBaudRate));
+
+    let config = SerialConfig { baud_rate: 1, ..SerialConfig::default() };
+    assert_eq!(config.divisor(), Err(SerialError::InvalidBaudRate));
+}
@@ -0,0 +1,426 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use lazy_static::lazy_static;
+use x86_64::instructions::interrupts;
+
+use crate::interrupts::{eoi, unmask_irq, InterruptIndex};
+use crate::interrupts::trap::TrapFrame;
+use crate::sync::IrqSpinLock;
+
+pub mod config;
+pub mod ring_buffer;
+pub mod uart;
+use ring_buffer::RingBuffer;
+use uart::{InterruptCause, Uart};
+
+pub use config::{DataBits, Parity, SerialConfig, SerialError, StopBits};
+
+#[macro_export]
+macro_rules! serial_print {
+    ($($arg:tt)*) => {
+        $crate::serial::_print(format_args!($($arg)*));
+    };
+}
+
+#[macro_export]
+macro_rules! channel_print {
+    ($channel:expr, $($arg:tt)*) => {
+        $crate::serial::_channel_print($channel, format_args!($($arg)*));
+    };
+}
+
+#[macro_export]
+macro_rules! serial_println {
+    () => ($crate::serial_print!("\n"));
+    ($fmt:expr) => ($crate::serial_print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => ($crate::serial_print!(
+        concat!($fmt, "\n"), $($arg)*));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Com {
+    Com1,
+    Com2,
+    Com3,
+    Com4,
+}
+
+pub const COM_PORTS: [Com; 4] = [Com::Com1, Com::Com2, Com::Com3, Com::Com4];
+
+impl Com {
+
+    pub fn base(&self) -> u16 {
+        return match self {
+            Com::Com1 => 0x3f8,
+            Com::Com2 => 0x2f8,
+            Com::Com3 => 0x3e8,
+            Com::Com4 => 0x2e8
+        };
+    }
+
+    // com1 and com3 share irq 4, com2 and com4 share irq 3
+    pub fn irq(&self) -> u8 {
+        return match self {
+            Com::Com1 | Com::Com3 => 4,
+            Com::Com2 | Com::Com4 => 3
+        };
+    }
+
+    fn index(&self) -> usize {
+        return *self as usize;
+    }
+}
+
+// what a piece of serial output is for, each can be sent to a different port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    KernelLog,
+    Debug,
+    Console,
+}
+
+lazy_static! {
+    // ports that did not pass the probe stay None
+    static ref PORTS: [IrqSpinLock<Option<SerialPort>>; 4] = {
+        let probe = |com: Com| {
+            let mut serial_port = unsafe { SerialPort::new(com.base()) };
+            if !serial_port.uart.probe() {
+                return IrqSpinLock::named("serial port", None);
+            }
+            serial_port.configure(&SerialConfig::default()).unwrap();
+            return IrqSpinLock::named("serial port", Some(serial_port));
+        };
+        return [probe(Com::Com1), probe(Com::Com2), probe(Com::Com3), probe(Com::Com4)];
+    };
+}
+
+// indexed by channel
+static ROUTES: IrqSpinLock<[Option<Com>; 3]> = IrqSpinLock::named(
+    "serial routes",
+    [Some(Com::Com1), Some(Com::Com2), Some(Com::Com1)]
+);
+
+// buffers both directions, the uart is polled until `enable_interrupts` is called
+pub struct SerialPort {
+    uart: Uart,
+    rx: RingBuffer,
+    tx: RingBuffer,
+    interrupt_driven: bool,
+}
+
+impl SerialPort {
+
+    pub const unsafe fn new(base: u16) -> SerialPort {
+        return SerialPort {
+            uart: Uart::new(base),
+            rx: RingBuffer::new(),
+            tx: RingBuffer::new(),
+            interrupt_driven: false
+        };
+    }
+
+    pub fn configure(&mut self, config: &SerialConfig) -> Result<(), SerialError> {
+        self.flush();
+        self.uart.init(config)?;
+        if self.interrupt_driven {
+            self.uart.set_interrupts(uart::RECEIVE_INTERRUPT);
+        }
+        return Ok(());
+    }
+
+    pub fn enable_interrupts(&mut self) {
+        self.interrupt_driven = true;
+        self.uart.set_interrupts(uart::RECEIVE_INTERRUPT);
+    }
+
+    pub fn send(&mut self, byte: u8) {
+        if !self.interrupt_driven {
+            self.uart.write_raw(byte);
+            return;
+        }
+        if self.tx.is_empty() && self.uart.can_write() {
+            self.uart.write_data(byte);
+            return;
+        }
+        if self.tx.is_full() {
+            // make room by waiting for the oldest byte to go out
+            let oldest = self.tx.pop().unwrap();
+            self.uart.write_raw(oldest);
+        }
+        self.tx.push(byte);
+        let interrupts = self.uart.interrupts();
+        self.uart.set_interrupts(interrupts | uart::TRANSMIT_INTERRUPT);
+    }
+
+    pub fn receive(&mut self) -> Option<u8> {
+        if let Some(byte) = self.rx.pop() {
+            return Some(byte);
+        }
+        if self.interrupt_driven {
+            return None;
+        }
+        return self.uart.try_read();
+    }
+
+    // bypasses the receive interrupt, for code running with interrupts off
+    pub fn poll_receive(&mut self) -> Option<u8> {
+        return self.rx.pop().or_else(|| self.uart.try_read());
+    }
+
+    // sends everything still queued by polling the uart
+    pub fn flush(&mut self) {
+        while let Some(byte) = self.tx.pop() {
+            self.uart.write_raw(byte);
+        }
+        let interrupts = self.uart.interrupts();
+        self.uart.set_interrupts(interrupts & !uart::TRANSMIT_INTERRUPT);
+    }
+
+    fn handle_interrupt(&mut self) {
+        while let Some(cause) = self.uart.interrupt_cause() {
+            match cause {
+                InterruptCause::ReceiveData | InterruptCause::CharacterTimeout => {
+                    while let Some(byte) = self.uart.try_read() {
+                        self.rx.push(byte);
+                    }
+                },
+                InterruptCause::TransmitEmpty => {
+                    for _ in 0..uart::FIFO_SIZE {
+                        match self.tx.pop() {
+                            Some(byte) => self.uart.write_data(byte),
+                            None => break
+                        }
+                    }
+                    if self.tx.is_empty() {
+                        let interrupts = self.uart.interrupts();
+                        self.uart.set_interrupts(interrupts & !uart::TRANSMIT_INTERRUPT);
+                    }
+                },
+                // reading the status registers acknowledges these
+                InterruptCause::LineStatus => {
+                    self.uart.line_status();
+                },
+                InterruptCause::ModemStatus => {
+                    self.uart.modem_status();
+                }
+            }
+        }
+    }
+}
+
+impl Write for SerialPort {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.send(byte);
+        }
+        return Ok(());
+    }
+}
+
+fn with_port<T>(com: Com, f: impl FnOnce(&mut SerialPort) -> T) -> Option<T> {
+    return PORTS[com.index()].lock().as_mut().map(f);
+}
+
+pub fn init() {
+    for com in COM_PORTS.iter() {
+        if with_port(*com, |port| port.enable_interrupts()).is_some() {
+            unmask_irq(com.irq());
+        }
+    }
+}
+
+pub fn is_present(com: Com) -> bool {
+    return with_port(com, |_| ()).is_some();
+}
+
+pub fn configure(com: Com, config: SerialConfig) -> Result<(), SerialError> {
+    return with_port(com, |port| port.configure(&config)).unwrap_or(Err(SerialError::NotPresent));
+}
+
+pub fn route(channel: Channel, com: Option<Com>) -> Result<(), SerialError> {
+    if let Some(com) = com {
+        if !is_present(com) {
+            return Err(SerialError::NotPresent);
+        }
+    }
+    ROUTES.lock()[channel as usize] = com;
+    return Ok(());
+}
+
+pub fn port_for(channel: Channel) -> Option<Com> {
+    return ROUTES.lock()[channel as usize];
+}
+
+pub fn flush() {
+    for com in COM_PORTS.iter() {
+        with_port(*com, |port| port.flush());
+    }
+}
+
+pub fn try_read_byte(channel: Channel) -> Option<u8> {
+    return with_port(port_for(channel)?, |port| port.receive())?;
+}
+
+pub fn poll_read_byte(channel: Channel) -> Option<u8> {
+    return with_port(port_for(channel)?, |port| port.poll_receive())?;
+}
+
+pub fn write_bytes(channel: Channel, bytes: &[u8]) {
+    let interrupts_enabled = interrupts::are_enabled();
+    if let Some(com) = port_for(channel) {
+        with_port(com, |port| {
+            for byte in bytes {
+                port.send(*byte);
+            }
+            if !interrupts_enabled {
+                port.flush();
+            }
+        });
+    }
+}
+
+// blocks until a byte arrives, sleeping between interrupts. a caller with interrupts
+// off gets the uart polled instead and finds them still off afterwards
+pub fn read_byte(channel: Channel) -> u8 {
+    let interrupts_enabled = interrupts::are_enabled();
+    loop {
+        interrupts::disable();
+        let byte = ROUTES.lock()[channel as usize].and_then(|com| {
+            return PORTS[com.index()].lock().as_mut().and_then(|port| match interrupts_enabled {
+                true => port.receive(),
+                false => port.poll_receive()
+            });
+        });
+        if let Some(byte) = byte {
+            if interrupts_enabled {
+                interrupts::enable();
+            }
+            return byte;
+        }
+        if interrupts_enabled {
+            interrupts::enable_and_hlt();
+        }
+        else {
+            core::sync::atomic::spin_loop_hint();
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LineEdit {
+    Echo(u8),
+    Erase,
+    Done,
+    Ignore,
+}
+
+// whether the last line ended in '\r', so the '\n' of a "\r\n" pair is skipped
+static AFTER_CARRIAGE_RETURN: AtomicBool = AtomicBool::new(false);
+
+fn edit_line(line: &mut Vec<u8>, byte: u8, after_carriage_return: bool) -> LineEdit {
+    return match byte {
+        b'\n' if after_carriage_return => LineEdit::Ignore,
+        b'\r' | b'\n' => LineEdit::Done,
+        0x08 | 0x7f => {
+            // drop a whole utf-8 sequence, not just its last byte
+            while let Some(last) = line.pop() {
+                if last & 0xc0 != 0x80 {
+                    return LineEdit::Erase;
+                }
+            }
+            LineEdit::Ignore
+        },
+        byte => {
+            line.push(byte);
+            LineEdit::Echo(byte)
+        }
+    };
+}
+
+// reads and echoes a line, handling backspace, without the line terminator
+pub fn read_line(channel: Channel) -> String {
+    let mut line = Vec::new();
+    loop {
+        let byte = read_byte(channel);
+        let after_carriage_return = AFTER_CARRIAGE_RETURN.swap(byte == b'\r', Ordering::Relaxed);
+        match edit_line(&mut line, byte, after_carriage_return) {
+            LineEdit::Echo(byte) => {
+                port_for(channel).and_then(|com| with_port(com, |port| port.send(byte)));
+            },
+            LineEdit::Erase => channel_print!(channel, "\x08 \x08"),
+            LineEdit::Done => {
+                channel_print!(channel, "\r\n");
+                return String::from_utf8_lossy(&line).into_owned();
+            },
+            LineEdit::Ignore => {}
+        }
+    }
+}
+
+fn handle_interrupts(ports: [Com; 2]) {
+    let _irq = crate::interrupts::IrqContext::enter();
+    for com in ports.iter() {
+        if let Some(port) = PORTS[com.index()].lock().as_mut() {
+            port.handle_interrupt();
+        }
+    }
+}
+
+pub(crate) fn com1_interrupt_handler(_frame: &mut TrapFrame) {
+    handle_interrupts([Com::Com1, Com::Com3]);
+    unsafe {
+        eoi(InterruptIndex::Com1 as u8);
+    }
+}
+
+pub(crate) fn com2_interrupt_handler(_frame: &mut TrapFrame) {
+    handle_interrupts([Com::Com2, Com::Com4]);
+    unsafe {
+        eoi(InterruptIndex::Com2 as u8);
+    }
+}
+
+pub fn _print(args: ::core::fmt::Arguments) {
+    _channel_print(Channel::Console, args);
+}
+
+pub fn _channel_print(channel: Channel, args: ::core::fmt::Arguments) {
+    let interrupts_enabled = interrupts::are_enabled();
+    let com = match port_for(channel) {
+        Some(com) => com,
+        None => return
+    };
+    if let Some(port) = PORTS[com.index()].lock().as_mut() {
+        port.write_fmt(args).expect("Printing to serial failed");
+        // nothing would drain the queue while interrupts stay off, e.g. when panicking
+        if !interrupts_enabled {
+            port.flush();
+        }
+    }
+}
+
+#[test_case]
+fn test_line_editing() {
+    let mut line = Vec::new();
+    for byte in "aé".bytes() {
+        assert_eq!(edit_line(&mut line, byte, false), LineEdit::Echo(byte));
+    }
+    assert_eq!(edit_line(&mut line, 0x7f, false), LineEdit::Erase);
+    assert_eq!(line, b"a");
+    assert_eq!(edit_line(&mut line, 0x08, false), LineEdit::Erase);
+    assert_eq!(edit_line(&mut line, 0x08, false), LineEdit::Ignore);
+    assert_eq!(edit_line(&mut line, b'\n', true), LineEdit::Ignore);
+    assert_eq!(edit_line(&mut line, b'\r', false), LineEdit::Done);
+}
+
+#[test_case]
+fn test_com1_is_detected() {
+    assert!(is_present(Com::Com1));
+    assert_eq!(port_for(Channel::Console), Some(Com::Com1));
+    if !is_present(Com::Com4) {
+        assert_eq!(route(Channel::Debug, Some(Com::Com4)), Err(SerialError::NotPresent));
+    }
+}
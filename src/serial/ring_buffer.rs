@@ -0,0 +1,82 @@
+pub const RING_BUFFER_SIZE: usize = 1024;
+
+pub struct RingBuffer {
+    bytes: [u8; RING_BUFFER_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+
+    pub const fn new() -> RingBuffer {
+        return RingBuffer {
+            bytes: [0; RING_BUFFER_SIZE],
+            head: 0,
+            len: 0
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    pub fn is_full(&self) -> bool {
+        return self.len == RING_BUFFER_SIZE;
+    }
+
+    // returns false and drops the byte when full
+    pub fn push(&mut self, byte: u8) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        self.bytes[(self.head + self.len) % RING_BUFFER_SIZE] = byte;
+        self.len = self.len + 1;
+        return true;
+    }
+
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.bytes[self.head];
+        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        self.len = self.len - 1;
+        return Some(byte);
+    }
+}
+
+#[test_case]
+fn test_ring_buffer_order() {
+    let mut ring = RingBuffer::new();
+    assert_eq!(ring.pop(), None);
+    for byte in b"abc" {
+        ring.push(*byte);
+    }
+    assert_eq!(ring.pop(), Some(b'a'));
+    ring.push(b'd');
+    assert_eq!(ring.len(), 3);
+    assert_eq!(ring.pop(), Some(b'b'));
+    assert_eq!(ring.pop(), Some(b'c'));
+    assert_eq!(ring.pop(), Some(b'd'));
+    assert!(ring.is_empty());
+}
+
+#[test_case]
+fn test_ring_buffer_wraps_and_fills() {
+    let mut ring = RingBuffer::new();
+    for i in 0..RING_BUFFER_SIZE + 10 {
+        ring.push(i as u8);
+        if i % 2 == 0 {
+            ring.pop();
+        }
+    }
+    while !ring.is_full() {
+        assert!(ring.push(0));
+    }
+    assert!(!ring.push(1));
+    assert_eq!(ring.len(), RING_BUFFER_SIZE);
+}
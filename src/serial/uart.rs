@@ -0,0 +1,137 @@
+use super::config::{SerialConfig, SerialError};
+
+use x86_64::instructions::port::Port;
+
+// register offsets from the port base, 0 and 1 hold the divisor while DLAB is set
+const DATA: u16 = 0;
+const INTERRUPT_ENABLE: u16 = 1;
+const INTERRUPT_ID: u16 = 2;
+const FIFO_CONTROL: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+const MODEM_STATUS: u16 = 6;
+const SCRATCH: u16 = 7;
+
+const DATA_READY: u8 = 0x01;
+const TRANSMIT_EMPTY: u8 = 0x20;
+
+pub const FIFO_SIZE: usize = 16;
+
+pub const RECEIVE_INTERRUPT: u8 = 0x01;
+pub const TRANSMIT_INTERRUPT: u8 = 0x02;
+
+// reasons reported by the interrupt identification register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptCause {
+    ModemStatus,
+    TransmitEmpty,
+    ReceiveData,
+    LineStatus,
+    CharacterTimeout,
+}
+
+// a 16550 compatible uart
+pub struct Uart {
+    base: u16,
+    interrupts: u8,
+}
+
+impl Uart {
+
+    pub const unsafe fn new(base: u16) -> Uart {
+        return Uart {
+            base,
+            interrupts: 0
+        };
+    }
+
+    fn register(&self, offset: u16) -> Port<u8> {
+        return Port::new(self.base + offset);
+    }
+
+    // a uart keeps whatever is written to its scratch register, an empty port floats
+    pub fn probe(&self) -> bool {
+        let mut scratch = self.register(SCRATCH);
+        for pattern in [0x55, 0xaa].iter() {
+            unsafe { scratch.write(*pattern) };
+            if unsafe { scratch.read() } != *pattern {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    pub fn init(&mut self, config: &SerialConfig) -> Result<(), SerialError> {
+        let divisor = config.divisor()?;
+        unsafe {
+            self.register(INTERRUPT_ENABLE).write(0x00);
+            self.register(LINE_CONTROL).write(0x80);
+            self.register(DATA).write(divisor as u8);
+            self.register(INTERRUPT_ENABLE).write((divisor >> 8) as u8);
+            self.register(LINE_CONTROL).write(config.line_control());
+            // enable and clear the fifos, interrupt at 14 received bytes
+            self.register(FIFO_CONTROL).write(0xc7);
+            // dtr, rts and out2, which gates the irq line
+            self.register(MODEM_CONTROL).write(0x0b);
+        }
+        self.interrupts = 0;
+        return Ok(());
+    }
+
+    pub fn set_interrupts(&mut self, interrupts: u8) {
+        if interrupts != self.interrupts {
+            unsafe { self.register(INTERRUPT_ENABLE).write(interrupts) };
+            self.interrupts = interrupts;
+        }
+    }
+
+    pub fn interrupts(&self) -> u8 {
+        return self.interrupts;
+    }
+
+    pub fn interrupt_cause(&self) -> Option<InterruptCause> {
+        let id = unsafe { self.register(INTERRUPT_ID).read() };
+        if id & 0x01 != 0 {
+            return None;
+        }
+        return match (id >> 1) & 0x07 {
+            0 => Some(InterruptCause::ModemStatus),
+            1 => Some(InterruptCause::TransmitEmpty),
+            2 => Some(InterruptCause::ReceiveData),
+            3 => Some(InterruptCause::LineStatus),
+            _ => Some(InterruptCause::CharacterTimeout)
+        };
+    }
+
+    pub fn line_status(&self) -> u8 {
+        return unsafe { self.register(LINE_STATUS).read() };
+    }
+
+    pub fn modem_status(&self) -> u8 {
+        return unsafe { self.register(MODEM_STATUS).read() };
+    }
+
+    pub fn can_write(&self) -> bool {
+        return self.line_status() & TRANSMIT_EMPTY != 0;
+    }
+
+    pub fn try_read(&mut self) -> Option<u8> {
+        if self.line_status() & DATA_READY == 0 {
+            return None;
+        }
+        return Some(unsafe { self.register(DATA).read() });
+    }
+
+    // only safe to call while the fifo has room, e.g. right after a transmit empty interrupt
+    pub fn write_data(&mut self, byte: u8) {
+        unsafe { self.register(DATA).write(byte) };
+    }
+
+    pub fn write_raw(&mut self, byte: u8) {
+        while !self.can_write() {
+            core::sync::atomic::spin_loop_hint();
+        }
+        unsafe { self.register(DATA).write(byte) };
+    }
+}
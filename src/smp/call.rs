@@ -0,0 +1,99 @@
+// runs a function on other cpus through the call function ipi and waits for it
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::interrupts::IrqContext;
+use crate::interrupts::apic::{self, IpiTarget};
+use crate::interrupts::trap::TrapFrame;
+use super::percpu::cpu_id;
+
+type Function = &'static (dyn Fn() + Sync);
+
+// one call at a time, the function and the cpus still running it
+static CALL: Mutex<()> = Mutex::new(());
+static FUNCTION: Mutex<Option<Function>> = Mutex::new(None);
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+pub fn call_on(cpu: usize, function: &(dyn Fn() + Sync)) {
+    call(1 << cpu, function);
+}
+
+pub fn call_on_others(function: &(dyn Fn() + Sync)) {
+    call(!(1 << cpu_id()), function);
+}
+
+pub fn call_on_all(function: &(dyn Fn() + Sync)) {
+    call(!0, function);
+}
+
+// `targets` has a bit per cpu, offline ones are left out. this cpu runs its share with
+// interrupts off like the others do in their handler
+fn call(targets: u64, function: &(dyn Fn() + Sync)) {
+    let this = 1 << cpu_id();
+    let remote = targets & super::online_mask() & !this;
+    if remote == 0 {
+        if targets & this != 0 {
+            interrupts::without_interrupts(|| function());
+        }
+        return;
+    }
+    // a cpu waiting here with interrupts off, as in any interrupt handler, could never
+    // run a call aimed at it
+    assert!(interrupts::are_enabled(), "cross-cpu calls need interrupts enabled");
+
+    let _call = CALL.lock();
+    // the caller waits below until every target is done with it
+    *FUNCTION.lock() = Some(unsafe { core::mem::transmute::<&(dyn Fn() + Sync), Function>(function) });
+    PENDING.store(remote, Ordering::Release);
+    if remote == super::online_mask() & !this {
+        unsafe { apic::send_ipi(IpiTarget::AllButSelf, apic::CALL_FUNCTION_VECTOR) };
+    } else {
+        for cpu in 0..super::percpu::MAX_CPUS {
+            if remote & 1 << cpu != 0 {
+                unsafe { apic::send_ipi(IpiTarget::Apic(super::apic_id(cpu)), apic::CALL_FUNCTION_VECTOR) };
+            }
+        }
+    }
+    if targets & this != 0 {
+        interrupts::without_interrupts(|| function());
+    }
+    while PENDING.load(Ordering::Acquire) != 0 {
+        core::sync::atomic::spin_loop_hint();
+    }
+    *FUNCTION.lock() = None;
+}
+
+pub(crate) fn call_function_interrupt_handler(_frame: &mut TrapFrame) {
+    let irq = IrqContext::enter();
+    let this = 1 << cpu_id();
+    if PENDING.load(Ordering::Acquire) & this != 0 {
+        let function = FUNCTION.lock().expect("call function ipi without a function");
+        function();
+        PENDING.fetch_and(!this, Ordering::AcqRel);
+    }
+    unsafe {
+        apic::eoi();
+    }
+    drop(irq);
+}
+
+#[test_case]
+fn test_call_on_self() {
+    use core::sync::atomic::AtomicUsize;
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    call_on(cpu_id(), &|| {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+    });
+    call_on_all(&|| {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+    });
+    // the tests run without the application processors
+    call_on_others(&|| {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+    });
+    assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+}
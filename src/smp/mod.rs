@@ -0,0 +1,135 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use spin::Mutex;
+use x86_64::VirtAddr;
+
+use crate::acpi::{self, AcpiError};
+use crate::interrupts::{self, apic::{self, ApicError}};
+use crate::{info, warn};
+
+pub mod call;
+pub mod percpu;
+pub mod tlb;
+mod trampoline;
+use percpu::PerCpu;
+use trampoline::Trampoline;
+pub use trampoline::TrampolineError;
+
+const AP_STACK_SIZE: usize = 4096 * 5;
+// how long an application processor gets to check in
+const STARTUP_TIMEOUT_MS: u64 = 1000;
+
+// a bit per cpu, the bootstrap processor is always online
+static ONLINE: AtomicU64 = AtomicU64::new(1);
+static APIC_IDS: Mutex<[u8; percpu::MAX_CPUS]> = Mutex::new([0; percpu::MAX_CPUS]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmpError {
+    Acpi(AcpiError),
+    Apic(ApicError),
+    Trampoline(TrampolineError),
+}
+
+// starts every enabled processor in the madt, returns how many cpus are online afterwards
+pub fn init() -> Result<usize, SmpError> {
+    let madt = acpi::madt().map_err(SmpError::Acpi)?;
+    apic::init(madt.local_apic_address).map_err(SmpError::Apic)?;
+    apic::enable();
+    let bsp = apic::id();
+    percpu::set_apic_id(bsp);
+    APIC_IDS.lock()[0] = bsp;
+
+    let trampoline = Trampoline::install().map_err(SmpError::Trampoline)?;
+    let application_processors = madt.processors.iter()
+        .filter(|processor| processor.enabled && processor.apic_id != bsp);
+    for (i, processor) in application_processors.enumerate() {
+        if i + 1 >= percpu::MAX_CPUS {
+            warn!("smp: only using the first {} cpus", percpu::MAX_CPUS);
+            break;
+        }
+        if !start(&trampoline, i + 1, processor.apic_id) {
+            warn!("smp: cpu with apic id {} did not come up", processor.apic_id);
+        }
+    }
+    return Ok(online());
+}
+
+pub fn online() -> usize {
+    return online_mask().count_ones() as usize;
+}
+
+pub fn online_mask() -> u64 {
+    return ONLINE.load(Ordering::Acquire);
+}
+
+pub fn apic_id(cpu: usize) -> u8 {
+    return APIC_IDS.lock()[cpu];
+}
+
+// the trampoline is shared, so processors are started one after another. the delays
+// are timed with the pit, so interrupts have to be enabled
+fn start(trampoline: &Trampoline, cpu: usize, apic_id: u8) -> bool {
+    assert!(x86_64::instructions::interrupts::are_enabled(), "starting a cpu needs the timer running");
+    let stack_top = leak_stack(AP_STACK_SIZE);
+    let per_cpu = percpu::new_ap(cpu, apic_id, leak_stack(crate::syscall::KERNEL_STACK_SIZE));
+    trampoline.prepare(stack_top, ap_main, per_cpu as *const PerCpu as u64);
+    APIC_IDS.lock()[cpu] = apic_id;
+
+    unsafe {
+        apic::send_init(apic_id);
+        wait_ms(10);
+        for _ in 0..2 {
+            apic::send_startup(apic_id, trampoline.vector());
+            // asks for 200 us, a tick is the shortest the pit can time
+            wait_ms(1);
+        }
+    }
+    let deadline = deadline(STARTUP_TIMEOUT_MS);
+    while interrupts::ticks() < deadline {
+        if online_mask() & 1 << cpu != 0 {
+            return true;
+        }
+        core::sync::atomic::spin_loop_hint();
+    }
+    return online_mask() & 1 << cpu != 0;
+}
+
+// never freed, a cpu can't be taken offline
+fn leak_stack(size: usize) -> VirtAddr {
+    let stack = Box::leak(vec![0u8; size].into_boxed_slice());
+    // the abi wants a 16 byte aligned stack
+    return VirtAddr::new((stack.as_ptr() as u64 + size as u64) & !0xf);
+}
+
+// the tick at which at least `ms` milliseconds have passed, the current one is already partly over
+fn deadline(ms: u64) -> u64 {
+    return interrupts::ticks() + interrupts::ms_to_ticks(ms) + 1;
+}
+
+fn wait_ms(ms: u64) {
+    let deadline = deadline(ms);
+    while interrupts::ticks() < deadline {
+        core::sync::atomic::spin_loop_hint();
+    }
+}
+
+extern "C" fn ap_main(argument: u64) -> ! {
+    // first, taking any lock reads the cpu id through gs
+    let per_cpu = unsafe { &*(argument as *const PerCpu) };
+    percpu::init_ap(per_cpu);
+    let cpu = per_cpu.cpu;
+    let apic_id = per_cpu.apic_id();
+
+    crate::gdt::init_ap();
+    crate::syscall::init_cpu();
+    crate::interrupts::init_idt();
+    apic::enable();
+    ONLINE.fetch_or(1 << cpu, Ordering::AcqRel);
+    info!("smp: cpu {} online (apic id {})", cpu, apic_id);
+
+    // the pics only deliver to the bootstrap processor, this cpu just answers ipis
+    x86_64::instructions::interrupts::enable();
+    crate::halt();
+}
@@ -0,0 +1,186 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+
+use x86_64::VirtAddr;
+use x86_64::registers::model_specific::{GsBase, KernelGsBase};
+
+// a bit per cpu in `CpuLocal::__initialized`
+pub const MAX_CPUS: usize = 64;
+
+// one per cpu, the gs base points at it while in the kernel. the layout is read
+// with gs relative loads, keep the first four fields where they are
+#[repr(C)]
+pub struct PerCpu {
+    self_ptr: *const PerCpu,
+    pub cpu: usize,
+    // gs:16 and gs:24 for `syscall_entry`, the user's rsp while it switches stacks
+    // and the top of this cpu's system call stack
+    syscall_user_rsp: AtomicU64,
+    syscall_kernel_rsp: AtomicU64,
+    apic_id: AtomicU8,
+}
+
+impl PerCpu {
+
+    const fn new(cpu: usize, apic_id: u8) -> PerCpu {
+        return PerCpu {
+            self_ptr: core::ptr::null(),
+            cpu,
+            syscall_user_rsp: AtomicU64::new(0),
+            syscall_kernel_rsp: AtomicU64::new(0),
+            apic_id: AtomicU8::new(apic_id)
+        };
+    }
+
+    pub fn apic_id(&self) -> u8 {
+        return self.apic_id.load(Ordering::Relaxed);
+    }
+}
+
+// the bootstrap processor's block, set up before there is a heap
+static mut BSP: PerCpu = PerCpu::new(0, 0);
+static BSP_LOADED: AtomicBool = AtomicBool::new(false);
+
+pub fn init_bsp() {
+    unsafe {
+        BSP.self_ptr = &BSP as *const PerCpu;
+        load(&BSP);
+    }
+    BSP_LOADED.store(true, Ordering::Release);
+}
+
+// until `gdt::init` loads the block nothing gs relative can be read, code that may run
+// that early, like the console and lockdep, checks this first. the other cpus load
+// theirs before they run anything that could get there
+pub fn is_loaded() -> bool {
+    return BSP_LOADED.load(Ordering::Acquire);
+}
+
+// built by the bootstrap processor, a cpu that has just started has no gs base yet
+// and can't take the heap lock to allocate its own
+pub fn new_ap(cpu: usize, apic_id: u8, syscall_stack_top: VirtAddr) -> &'static PerCpu {
+    let per_cpu = Box::leak(Box::new(PerCpu::new(cpu, apic_id)));
+    per_cpu.self_ptr = per_cpu as *const PerCpu;
+    per_cpu.syscall_kernel_rsp.store(syscall_stack_top.as_u64(), Ordering::Relaxed);
+    return per_cpu;
+}
+
+pub fn init_ap(per_cpu: &'static PerCpu) {
+    load(per_cpu);
+}
+
+pub fn set_apic_id(apic_id: u8) {
+    this_cpu().apic_id.store(apic_id, Ordering::Relaxed);
+}
+
+pub fn set_syscall_stack(stack_top: VirtAddr) {
+    this_cpu().syscall_kernel_rsp.store(stack_top.as_u64(), Ordering::Relaxed);
+}
+
+// ring 3 can load a null gs selector and zero its base, so it never gets the block.
+// the user's base of 0 waits in the kernel gs base, and every way in from ring 3,
+// the stubs in `interrupts::trap` and `syscall_entry`, swaps the two
+fn load(per_cpu: &'static PerCpu) {
+    GsBase::write(VirtAddr::from_ptr(per_cpu as *const PerCpu));
+    KernelGsBase::write(VirtAddr::new(0));
+}
+
+pub fn this_cpu() -> &'static PerCpu {
+    let per_cpu: *const PerCpu;
+    unsafe {
+        asm!("mov {}, qword ptr gs:[0]", out(reg) per_cpu, options(nostack, readonly, preserves_flags));
+        return &*per_cpu;
+    }
+}
+
+pub fn cpu_id() -> usize {
+    let cpu: usize;
+    unsafe {
+        asm!("mov {}, qword ptr gs:[8]", out(reg) cpu, options(nostack, readonly, preserves_flags));
+    }
+    return cpu;
+}
+
+// a value per cpu, declared with `cpu_local!`. each cpu builds its own copy on first
+// use and only ever touches that one, so interrupt handlers can use it too
+pub struct CpuLocal<T: 'static> {
+    #[doc(hidden)]
+    pub __init: fn() -> T,
+    #[doc(hidden)]
+    pub __values: UnsafeCell<MaybeUninit<[T; MAX_CPUS]>>,
+    #[doc(hidden)]
+    pub __initialized: AtomicU64,
+}
+
+unsafe impl<T: 'static> Sync for CpuLocal<T> {}
+
+impl<T: 'static> CpuLocal<T> {
+
+    // the copy of the cpu this runs on, kernel threads stay on the bootstrap processor
+    pub fn get(&'static self) -> &'static T {
+        let cpu = cpu_id();
+        let slot = unsafe { (self.__values.get() as *mut T).add(cpu) };
+        if self.__initialized.load(Ordering::Acquire) & 1 << cpu == 0 {
+            // an interrupt handler using it in between would build a second copy
+            x86_64::instructions::interrupts::without_interrupts(|| {
+                if self.__initialized.load(Ordering::Acquire) & 1 << cpu == 0 {
+                    unsafe { slot.write((self.__init)()) };
+                    self.__initialized.fetch_or(1 << cpu, Ordering::AcqRel);
+                }
+            });
+        }
+        return unsafe { &*slot };
+    }
+
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        return f(self.get());
+    }
+}
+
+#[macro_export]
+macro_rules! cpu_local {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr;)*) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::smp::percpu::CpuLocal<$ty> = {
+                fn init() -> $ty {
+                    return $init;
+                }
+                $crate::smp::percpu::CpuLocal {
+                    __init: init,
+                    __values: core::cell::UnsafeCell::new(core::mem::MaybeUninit::uninit()),
+                    __initialized: core::sync::atomic::AtomicU64::new(0)
+                }
+            };
+        )*
+    };
+}
+
+#[cfg(test)]
+use core::cell::Cell;
+
+#[cfg(test)]
+cpu_local! {
+    static TEST_COUNTER: Cell<u64> = Cell::new(40);
+}
+
+#[test_case]
+fn test_cpu_local_keeps_its_value() {
+    TEST_COUNTER.with(|counter| counter.set(counter.get() + 1));
+    TEST_COUNTER.with(|counter| counter.set(counter.get() + 1));
+    assert_eq!(TEST_COUNTER.get().get(), 42);
+}
+
+#[test_case]
+fn test_bootstrap_processor_is_cpu_zero() {
+    assert_eq!(cpu_id(), 0);
+    assert_eq!(this_cpu().cpu, 0);
+}
+
+#[test_case]
+fn test_user_gs_base_stays_zero() {
+    assert_eq!(KernelGsBase::read(), VirtAddr::new(0));
+    assert_eq!(GsBase::read(), VirtAddr::from_ptr(this_cpu() as *const PerCpu));
+}
@@ -0,0 +1,41 @@
+use core::cell::Cell;
+
+use x86_64::instructions::tlb;
+use x86_64::structures::paging::PageRangeInclusive;
+
+use crate::cpu_local;
+
+// past this many pages reloading cr3 is cheaper than flushing one by one
+const FLUSH_ALL_THRESHOLD: u64 = 32;
+
+cpu_local! {
+    // shootdowns this cpu took part in on behalf of another
+    static FLUSHES: Cell<u64> = Cell::new(0);
+}
+
+// every other cpu drops its cached translations for `pages` before this returns,
+// the caller has changed the tables and flushed its own tlb already
+pub fn shootdown(pages: PageRangeInclusive) {
+    if super::online_mask().count_ones() < 2 {
+        return;
+    }
+    super::call::call_on_others(&|| {
+        flush(pages);
+        FLUSHES.with(|flushes| flushes.set(flushes.get() + 1));
+    });
+}
+
+pub fn flushes() -> u64 {
+    return FLUSHES.get().get();
+}
+
+pub fn flush(pages: PageRangeInclusive) {
+    let count = pages.end - pages.start + 1;
+    if count > FLUSH_ALL_THRESHOLD {
+        tlb::flush_all();
+        return;
+    }
+    for page in pages {
+        tlb::flush(page.start_address());
+    }
+}
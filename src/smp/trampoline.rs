@@ -0,0 +1,178 @@
+use x86_64::registers::control::Cr3;
+use x86_64::structures::paging::{PageTableFlags, PhysFrame};
+use x86_64::VirtAddr;
+
+// application processors wake up in real mode at the start of `frame`. the code
+// switches straight to long mode on the kernel page tables with a throwaway gdt,
+// then calls the entry point on its own stack with the argument in rdi.
+// the gdt pointer and far jump need absolute addresses, patched in by `install`
+global_asm!(r#"
+.pushsection .rodata.trampoline, "a"
+.global trampoline_start
+.global trampoline_end
+.global trampoline_gdt
+.global trampoline_gdt_pointer
+.global trampoline_long_mode
+.global trampoline_long_mode_pointer
+.global trampoline_page_table
+.global trampoline_stack
+.global trampoline_entry
+.global trampoline_argument
+
+.code16
+trampoline_start:
+    cli
+    cld
+    movw %cs, %ax
+    movw %ax, %ds
+    lgdtl trampoline_gdt_pointer - trampoline_start
+
+    # pae
+    movl %cr4, %eax
+    orl $0x20, %eax
+    movl %eax, %cr4
+
+    movl trampoline_page_table - trampoline_start, %eax
+    movl %eax, %cr3
+
+    # long mode and no-execute, the kernel tables use the nx bit
+    movl $0xc0000080, %ecx
+    rdmsr
+    orl $0x900, %eax
+    wrmsr
+
+    # protection and paging at once
+    movl %cr0, %eax
+    orl $0x80000001, %eax
+    movl %eax, %cr0
+
+    ljmpl *trampoline_long_mode_pointer - trampoline_start
+
+.code64
+trampoline_long_mode:
+    movw $0x10, %ax
+    movw %ax, %ds
+    movw %ax, %es
+    movw %ax, %ss
+    movq trampoline_stack(%rip), %rsp
+    movq trampoline_argument(%rip), %rdi
+    movq trampoline_entry(%rip), %rax
+    callq *%rax
+    ud2
+
+.align 8
+trampoline_gdt:
+    .quad 0
+    .quad 0x00af9a000000ffff
+    .quad 0x00cf92000000ffff
+trampoline_gdt_pointer:
+    .word 23
+    .long 0
+trampoline_long_mode_pointer:
+    .long 0
+    .word 0x08
+
+.align 8
+trampoline_page_table:
+    .quad 0
+trampoline_stack:
+    .quad 0
+trampoline_entry:
+    .quad 0
+trampoline_argument:
+    .quad 0
+trampoline_end:
+.popsection
+"#);
+
+extern "C" {
+    static trampoline_start: u8;
+    static trampoline_end: u8;
+    static trampoline_gdt: u8;
+    static trampoline_gdt_pointer: u8;
+    static trampoline_long_mode: u8;
+    static trampoline_long_mode_pointer: u8;
+    static trampoline_page_table: u8;
+    static trampoline_stack: u8;
+    static trampoline_entry: u8;
+    static trampoline_argument: u8;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrampolineError {
+    NoLowMemory,
+    PageTableTooHigh,
+    MappingFailed,
+}
+
+pub struct Trampoline {
+    frame: PhysFrame,
+}
+
+impl Trampoline {
+
+    // copies the code into the low frame, identity mapped so it survives turning on paging
+    pub fn install() -> Result<Trampoline, TrampolineError> {
+        let frame = crate::memory::low_frame().ok_or(TrampolineError::NoLowMemory)?;
+        let physical = frame.start_address();
+        let (page_table, _) = Cr3::read();
+        // cr3 is loaded while still in 32 bit mode
+        if page_table.start_address().as_u64() > u32::max_value() as u64 {
+            return Err(TrampolineError::PageTableTooHigh);
+        }
+        crate::memory::map_physical_region(
+            physical,
+            4096,
+            VirtAddr::new(physical.as_u64()),
+            PageTableFlags::PRESENT | PageTableFlags::WRITABLE
+        ).map_err(|_| TrampolineError::MappingFailed)?;
+
+        let trampoline = Trampoline { frame };
+        unsafe {
+            let start = &trampoline_start as *const u8;
+            let size = &trampoline_end as *const u8 as usize - start as usize;
+            core::ptr::copy_nonoverlapping(start, trampoline.base(), size);
+            trampoline.write(&trampoline_page_table, page_table.start_address().as_u64());
+            let gdt = trampoline.physical(&trampoline_gdt) as u32;
+            let long_mode = trampoline.physical(&trampoline_long_mode) as u32;
+            trampoline.write_u32(&trampoline_gdt_pointer, 2, gdt);
+            trampoline.write_u32(&trampoline_long_mode_pointer, 0, long_mode);
+        }
+        return Ok(trampoline);
+    }
+
+    // the startup ipi vector is the page number of the code
+    pub fn vector(&self) -> u8 {
+        return (self.frame.start_address().as_u64() >> 12) as u8;
+    }
+
+    pub fn prepare(&self, stack_top: VirtAddr, entry: extern "C" fn(u64) -> !, argument: u64) {
+        unsafe {
+            self.write(&trampoline_stack, stack_top.as_u64());
+            self.write(&trampoline_entry, entry as usize as u64);
+            self.write(&trampoline_argument, argument);
+        }
+    }
+
+    fn base(&self) -> *mut u8 {
+        return crate::memory::phys_to_virt(self.frame.start_address()).as_mut_ptr();
+    }
+
+    unsafe fn offset(&self, symbol: &u8) -> usize {
+        return symbol as *const u8 as usize - &trampoline_start as *const u8 as usize;
+    }
+
+    unsafe fn physical(&self, symbol: &u8) -> u64 {
+        return self.frame.start_address().as_u64() + self.offset(symbol) as u64;
+    }
+
+    unsafe fn write(&self, symbol: &u8, value: u64) {
+        let address = self.base().add(self.offset(symbol)) as *mut u64;
+        core::ptr::write_volatile(address, value);
+    }
+
+    unsafe fn write_u32(&self, symbol: &u8, extra: usize, value: u32) {
+        let address = self.base().add(self.offset(symbol) + extra) as *mut u32;
+        core::ptr::write_unaligned(address, value);
+    }
+}
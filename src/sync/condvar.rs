@@ -0,0 +1,63 @@
+use x86_64::instructions::interrupts;
+
+use super::{MutexGuard, WaitQueue};
+
+pub struct Condvar {
+    waiters: WaitQueue,
+}
+
+impl Condvar {
+
+    pub const fn new() -> Condvar {
+        return Condvar {
+            waiters: WaitQueue::new()
+        };
+    }
+
+    // unlocks the mutex and sleeps until notified, then locks it again;
+    // wakeups can be spurious, see `wait_while`
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex();
+        // queued before the unlock so a notify right after it isn't lost
+        interrupts::without_interrupts(|| {
+            self.waiters.sleep_after(|| drop(guard));
+        });
+        return mutex.lock();
+    }
+
+    pub fn wait_while<'a, T>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: impl FnMut(&mut T) -> bool
+    ) -> MutexGuard<'a, T> {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        return guard;
+    }
+
+    pub fn notify_one(&self) -> bool {
+        return self.waiters.notify_one();
+    }
+
+    pub fn notify_all(&self) -> usize {
+        return self.waiters.notify_all();
+    }
+}
+
+#[test_case]
+fn test_condvar_hands_over_value() {
+    use super::Mutex;
+    use crate::thread;
+
+    static SLOT: Mutex<Option<u64>> = Mutex::new(None);
+    static FILLED: Condvar = Condvar::new();
+    let consumer = thread::spawn(|| {
+        let mut slot = FILLED.wait_while(SLOT.lock(), |slot| slot.is_none());
+        return slot.take().unwrap();
+    });
+    thread::yield_now();
+    *SLOT.lock() = Some(7);
+    FILLED.notify_all();
+    assert_eq!(consumer.join(), 7);
+}
@@ -0,0 +1,112 @@
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::panic::Location;
+
+use x86_64::instructions::interrupts;
+
+use super::{lock_acquired, lock_released, LockKind};
+
+// a spinlock that keeps interrupts disabled while held, so a handler on the
+// same cpu can never spin on a lock the code it interrupted is holding
+pub struct IrqSpinLock<T> {
+    inner: spin::Mutex<T>,
+    name: Option<&'static str>,
+}
+
+pub struct IrqSpinLockGuard<'a, T> {
+    guard: ManuallyDrop<spin::MutexGuard<'a, T>>,
+    // whether interrupts were on before the lock was taken
+    enabled: bool,
+    name: Option<&'static str>,
+}
+
+impl<T> IrqSpinLock<T> {
+
+    pub const fn new(value: T) -> IrqSpinLock<T> {
+        return IrqSpinLock {
+            inner: spin::Mutex::new(value),
+            name: None
+        };
+    }
+
+    pub const fn named(name: &'static str, value: T) -> IrqSpinLock<T> {
+        return IrqSpinLock {
+            inner: spin::Mutex::new(value),
+            name: Some(name)
+        };
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> IrqSpinLockGuard<T> {
+        let enabled = interrupts::are_enabled();
+        interrupts::disable();
+        lock_acquired(self.name, LockKind::Spin, false, true, Location::caller());
+        return IrqSpinLockGuard {
+            guard: ManuallyDrop::new(self.inner.lock()),
+            enabled,
+            name: self.name
+        };
+    }
+
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<IrqSpinLockGuard<T>> {
+        let enabled = interrupts::are_enabled();
+        interrupts::disable();
+        return match self.inner.try_lock() {
+            Some(guard) => {
+                lock_acquired(self.name, LockKind::Spin, false, false, Location::caller());
+                Some(IrqSpinLockGuard { guard: ManuallyDrop::new(guard), enabled, name: self.name })
+            },
+            None => {
+                if enabled {
+                    interrupts::enable();
+                }
+                None
+            }
+        };
+    }
+}
+
+impl<'a, T> Deref for IrqSpinLockGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        return &self.guard;
+    }
+}
+
+impl<'a, T> DerefMut for IrqSpinLockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        return &mut self.guard;
+    }
+}
+
+impl<'a, T> Drop for IrqSpinLockGuard<'a, T> {
+    fn drop(&mut self) {
+        lock_released(self.name);
+        // unlocked before interrupts come back on
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        if self.enabled {
+            interrupts::enable();
+        }
+    }
+}
+
+#[test_case]
+fn test_irq_spin_lock_restores_interrupts() {
+    let lock = IrqSpinLock::new(1);
+    assert!(interrupts::are_enabled());
+    {
+        let mut value = lock.lock();
+        assert!(!interrupts::are_enabled());
+        assert!(lock.try_lock().is_none());
+        *value = 2;
+    }
+    assert!(interrupts::are_enabled());
+    interrupts::without_interrupts(|| {
+        let value = lock.try_lock().unwrap();
+        assert_eq!(*value, 2);
+        drop(value);
+        assert!(!interrupts::are_enabled());
+    });
+}
@@ -0,0 +1,405 @@
+// lock validator for debug builds: every named lock is a class, acquiring one
+// while holding another records an order between the two classes, and an
+// acquisition that could deadlock is reported over serial once
+
+use core::fmt::{self, Write};
+use core::panic::Location;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use x86_64::instructions::interrupts;
+
+use crate::smp::percpu::{self, MAX_CPUS};
+use crate::thread::{self, ThreadId, MAX_THREADS};
+
+pub const MAX_CLASSES: usize = 128;
+pub const MAX_HELD: usize = 16;
+
+const WORDS: usize = MAX_CLASSES / 64;
+
+// taken in interrupt context, and held with interrupts enabled outside of it
+const USED_IN_IRQ: u8 = 1 << 0;
+const USED_WITH_IRQS_ON: u8 = 1 << 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    // a spinlock that disables interrupts, or one that can't be held across a switch
+    Spin,
+    // may put the thread to sleep
+    Sleeping,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    Recursive { class: &'static str },
+    // `acquiring` was taken while holding `held` before, in the other order
+    Inversion { held: &'static str, acquiring: &'static str },
+    IrqUnsafe { class: &'static str },
+    SleepInIrq { class: &'static str },
+    TooManyClasses,
+    TooManyHeld,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Violation::Recursive { class } => write!(f, "recursive locking of {}", class),
+            Violation::Inversion { held, acquiring } => write!(
+                f, "lock order inversion: {} taken while holding {}, which was earlier taken while holding {}",
+                acquiring, held, acquiring
+            ),
+            Violation::IrqUnsafe { class } => write!(
+                f, "{} is taken in interrupt context and held elsewhere with interrupts enabled", class
+            ),
+            Violation::SleepInIrq { class } => write!(f, "sleeping lock {} taken in interrupt context", class),
+            Violation::TooManyClasses => write!(f, "lock class table full"),
+            Violation::TooManyHeld => write!(f, "too many locks held")
+        };
+    }
+}
+
+// who holds a lock: the thread, and the cpu it runs on. the other cpus' handlers
+// don't interrupt it, so they can't share its stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Owner {
+    pub cpu: usize,
+    pub thread: ThreadId,
+}
+
+impl Owner {
+
+    // kernel threads only run on the bootstrap processor, the others just idle and take ipis
+    fn current() -> Owner {
+        if !percpu::is_loaded() {
+            return Owner { cpu: 0, thread: thread::current() };
+        }
+        let cpu = percpu::cpu_id();
+        let thread = if cpu == 0 { thread::current() } else { ThreadId::MAIN };
+        return Owner { cpu, thread };
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Held {
+    class: usize,
+    location: Option<&'static Location<'static>>,
+}
+
+#[derive(Clone, Copy)]
+struct HeldStack {
+    owner: Option<Owner>,
+    depth: usize,
+    locks: [Held; MAX_HELD],
+}
+
+const NO_LOCKS: HeldStack = HeldStack {
+    owner: None,
+    depth: 0,
+    locks: [Held { class: 0, location: None }; MAX_HELD]
+};
+
+pub struct Lockdep {
+    classes: [Option<&'static str>; MAX_CLASSES],
+    usage: [u8; MAX_CLASSES],
+    // after[a] has bit b set when b was taken while a was held
+    after: [[u64; WORDS]; MAX_CLASSES],
+    // one stack per thread and one per application processor, interrupt handlers
+    // push onto the one they interrupted
+    held: [HeldStack; MAX_THREADS + MAX_CPUS],
+}
+
+impl Lockdep {
+
+    pub const fn new() -> Lockdep {
+        return Lockdep {
+            classes: [None; MAX_CLASSES],
+            usage: [0; MAX_CLASSES],
+            after: [[0; WORDS]; MAX_CLASSES],
+            held: [NO_LOCKS; MAX_THREADS + MAX_CPUS]
+        };
+    }
+
+    fn class(&mut self, name: &'static str) -> Result<usize, Violation> {
+        for (index, class) in self.classes.iter().enumerate() {
+            match class {
+                Some(class) if *class == name => return Ok(index),
+                Some(_) => {},
+                None => {
+                    self.classes[index] = Some(name);
+                    return Ok(index);
+                }
+            }
+        }
+        return Err(Violation::TooManyClasses);
+    }
+
+    fn stack(&mut self, owner: Owner) -> Result<&mut HeldStack, Violation> {
+        let index = self.held.iter().position(|stack| stack.owner == Some(owner))
+            .or_else(|| self.held.iter().position(|stack| stack.owner.is_none() || stack.depth == 0))
+            .ok_or(Violation::TooManyHeld)?;
+        let stack = &mut self.held[index];
+        stack.owner = Some(owner);
+        return Ok(stack);
+    }
+
+    fn depends(&self, from: usize, to: usize) -> bool {
+        return self.after[from][to / 64] & (1 << (to % 64)) != 0;
+    }
+
+    // whether `to` was ever taken, directly or through other locks, while `from` was held
+    fn reachable(&self, from: usize, to: usize) -> bool {
+        let mut visited = [0u64; WORDS];
+        let mut pending = [0usize; MAX_CLASSES];
+        let mut len = 1;
+        pending[0] = from;
+        visited[from / 64] |= 1 << (from % 64);
+        while len > 0 {
+            len = len - 1;
+            let class = pending[len];
+            if class == to {
+                return true;
+            }
+            for next in 0..MAX_CLASSES {
+                if self.depends(class, next) && visited[next / 64] & (1 << (next % 64)) == 0 {
+                    visited[next / 64] |= 1 << (next % 64);
+                    pending[len] = next;
+                    len = len + 1;
+                }
+            }
+        }
+        return false;
+    }
+
+    fn name(&self, class: usize) -> &'static str {
+        return self.classes[class].unwrap_or("?");
+    }
+
+    // checks and records taking `name`, trylocks and shared acquisitions can't
+    // deadlock on their own so they pass `check` false and are only recorded
+    pub fn acquire(
+        &mut self,
+        owner: Owner,
+        name: &'static str,
+        kind: Kind,
+        in_irq: bool,
+        irqs_enabled: bool,
+        check: bool,
+        location: &'static Location<'static>
+    ) -> Result<(), Violation> {
+        let class = self.class(name)?;
+        if kind == Kind::Sleeping && in_irq {
+            return Err(Violation::SleepInIrq { class: name });
+        }
+
+        let previous = self.usage[class];
+        // a handler that only ever trylocks gives up instead of spinning on the code it interrupted
+        if in_irq && check {
+            self.usage[class] |= USED_IN_IRQ;
+        } else if irqs_enabled {
+            self.usage[class] |= USED_WITH_IRQS_ON;
+        }
+        let both = USED_IN_IRQ | USED_WITH_IRQS_ON;
+        if self.usage[class] == both && previous != both {
+            return Err(Violation::IrqUnsafe { class: name });
+        }
+
+        let stack = *self.stack(owner)?;
+        if check {
+            for held in stack.locks[..stack.depth].iter() {
+                if held.class == class {
+                    return Err(Violation::Recursive { class: name });
+                }
+                if !self.depends(held.class, class) {
+                    if self.reachable(class, held.class) {
+                        return Err(Violation::Inversion { held: self.name(held.class), acquiring: name });
+                    }
+                    self.after[held.class][class / 64] |= 1 << (class % 64);
+                }
+            }
+        }
+
+        let stack = self.stack(owner)?;
+        if stack.depth == MAX_HELD {
+            return Err(Violation::TooManyHeld);
+        }
+        stack.locks[stack.depth] = Held { class, location: Some(location) };
+        stack.depth = stack.depth + 1;
+        return Ok(());
+    }
+
+    // locks don't have to be released in order
+    pub fn release(&mut self, owner: Owner, name: &'static str) {
+        let class = match self.classes.iter().position(|class| *class == Some(name)) {
+            Some(class) => class,
+            None => return
+        };
+        let stack = match self.held.iter_mut().find(|stack| stack.owner == Some(owner)) {
+            Some(stack) => stack,
+            None => return
+        };
+        if let Some(index) = stack.locks[..stack.depth].iter().rposition(|held| held.class == class) {
+            stack.locks.copy_within(index + 1..stack.depth, index);
+            stack.depth = stack.depth - 1;
+        }
+        if stack.depth == 0 {
+            stack.owner = None;
+        }
+    }
+
+    fn write_held(&self, owner: Owner, out: &mut impl fmt::Write) -> fmt::Result {
+        let stack = match self.held.iter().find(|stack| stack.owner == Some(owner)) {
+            Some(stack) => stack,
+            None => return writeln!(out, "no locks held")
+        };
+        writeln!(out, "locks held by {:?} on cpu {}:", owner.thread, owner.cpu)?;
+        for held in stack.locks[..stack.depth].iter().rev() {
+            match held.location {
+                Some(location) => writeln!(out, "  {} at {}", self.name(held.class), location)?,
+                None => writeln!(out, "  {}", self.name(held.class))?
+            }
+        }
+        return Ok(());
+    }
+}
+
+static LOCKDEP: spin::Mutex<Lockdep> = spin::Mutex::new(Lockdep::new());
+
+// cleared after the first report, the recorded state can't be trusted after it
+static ENABLED: AtomicBool = AtomicBool::new(true);
+// locks taken while printing a report are not tracked
+static REPORTING: AtomicBool = AtomicBool::new(false);
+
+pub fn is_enabled() -> bool {
+    return ENABLED.load(Ordering::Relaxed);
+}
+
+pub(crate) fn acquire(
+    name: &'static str,
+    kind: Kind,
+    irqs_enabled: bool,
+    check: bool,
+    location: &'static Location<'static>
+) {
+    if !is_enabled() || REPORTING.load(Ordering::Relaxed) {
+        return;
+    }
+    let in_irq = crate::interrupts::in_interrupt();
+    interrupts::without_interrupts(|| {
+        let owner = Owner::current();
+        let result = match LOCKDEP.try_lock() {
+            Some(mut lockdep) => lockdep.acquire(owner, name, kind, in_irq, irqs_enabled, check, location),
+            None => return
+        };
+        if let Err(violation) = result {
+            report(owner, violation, name, location);
+        }
+    });
+}
+
+pub(crate) fn release(name: &'static str) {
+    if !is_enabled() || REPORTING.load(Ordering::Relaxed) {
+        return;
+    }
+    interrupts::without_interrupts(|| {
+        let owner = Owner::current();
+        if let Some(mut lockdep) = LOCKDEP.try_lock() {
+            lockdep.release(owner, name);
+        }
+    });
+}
+
+fn report(owner: Owner, violation: Violation, name: &'static str, location: &'static Location<'static>) {
+    ENABLED.store(false, Ordering::Relaxed);
+    REPORTING.store(true, Ordering::Relaxed);
+    let mut out = ReportWriter;
+    let _ = writeln!(out, "\nlockdep: {}", violation);
+    let _ = writeln!(out, "{:?} on cpu {} acquiring {} at {}", owner.thread, owner.cpu, name, location);
+    if let Some(lockdep) = LOCKDEP.try_lock() {
+        let _ = lockdep.write_held(owner, &mut out);
+    }
+    let _ = writeln!(out, "lockdep disabled");
+    REPORTING.store(false, Ordering::Relaxed);
+}
+
+struct ReportWriter;
+
+impl fmt::Write for ReportWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        crate::serial::_channel_print(crate::serial::Channel::KernelLog, format_args!("{}", s));
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+const TEST_OWNER: Owner = Owner { cpu: 0, thread: ThreadId::MAIN };
+
+#[cfg(test)]
+static TEST_LOCKDEP: spin::Mutex<Lockdep> = spin::Mutex::new(Lockdep::new());
+
+#[test_case]
+fn test_lockdep_order_inversion() {
+    let mut lockdep = TEST_LOCKDEP.lock();
+    let here = Location::caller();
+    let owner = TEST_OWNER;
+    assert_eq!(lockdep.acquire(owner, "test a", Kind::Spin, false, false, true, here), Ok(()));
+    assert_eq!(lockdep.acquire(owner, "test b", Kind::Spin, false, false, true, here), Ok(()));
+    lockdep.release(owner, "test a");
+    lockdep.release(owner, "test b");
+    assert_eq!(lockdep.acquire(owner, "test b", Kind::Spin, false, false, true, here), Ok(()));
+    assert_eq!(
+        lockdep.acquire(owner, "test a", Kind::Spin, false, false, true, here),
+        Err(Violation::Inversion { held: "test b", acquiring: "test a" })
+    );
+    lockdep.release(owner, "test b");
+}
+
+#[test_case]
+fn test_lockdep_recursion_and_trylock() {
+    let mut lockdep = TEST_LOCKDEP.lock();
+    let here = Location::caller();
+    let owner = TEST_OWNER;
+    assert_eq!(lockdep.acquire(owner, "test c", Kind::Sleeping, false, true, true, here), Ok(()));
+    assert_eq!(
+        lockdep.acquire(owner, "test c", Kind::Sleeping, false, true, true, here),
+        Err(Violation::Recursive { class: "test c" })
+    );
+    assert_eq!(lockdep.acquire(owner, "test c", Kind::Sleeping, false, true, false, here), Ok(()));
+    lockdep.release(owner, "test c");
+    lockdep.release(owner, "test c");
+}
+
+#[test_case]
+fn test_lockdep_irq_usage() {
+    let mut lockdep = TEST_LOCKDEP.lock();
+    let here = Location::caller();
+    let owner = TEST_OWNER;
+    assert_eq!(lockdep.acquire(owner, "test d", Kind::Spin, true, false, true, here), Ok(()));
+    lockdep.release(owner, "test d");
+    // interrupts off outside the handler is fine
+    assert_eq!(lockdep.acquire(owner, "test d", Kind::Spin, false, false, true, here), Ok(()));
+    lockdep.release(owner, "test d");
+    assert_eq!(
+        lockdep.acquire(owner, "test d", Kind::Spin, false, true, true, here),
+        Err(Violation::IrqUnsafe { class: "test d" })
+    );
+    // trylocks in a handler don't count as interrupt usage
+    assert_eq!(lockdep.acquire(owner, "test f", Kind::Spin, true, false, false, here), Ok(()));
+    lockdep.release(owner, "test f");
+    assert_eq!(lockdep.acquire(owner, "test f", Kind::Spin, false, true, true, here), Ok(()));
+    lockdep.release(owner, "test f");
+    assert_eq!(
+        lockdep.acquire(owner, "test e", Kind::Sleeping, true, false, true, here),
+        Err(Violation::SleepInIrq { class: "test e" })
+    );
+}
+
+#[test_case]
+fn test_lockdep_keeps_a_stack_per_cpu() {
+    let mut lockdep = TEST_LOCKDEP.lock();
+    let here = Location::caller();
+    let other = Owner { cpu: 1, thread: ThreadId::MAIN };
+    assert_eq!(lockdep.acquire(TEST_OWNER, "test g", Kind::Spin, false, false, true, here), Ok(()));
+    // the same class taken on another cpu is contention, not recursion
+    assert_eq!(lockdep.acquire(other, "test g", Kind::Spin, false, false, true, here), Ok(()));
+    lockdep.release(other, "test g");
+    lockdep.release(TEST_OWNER, "test g");
+}
@@ -0,0 +1,64 @@
+// locks for kernel code: `IrqSpinLock` for data shared with interrupt handlers,
+// `SpinLock` where the callers manage interrupts themselves, the
+// blocking ones put the calling thread to sleep instead of spinning.
+//
+// every lock has a `named` constructor, lockdep keeps track of the named ones and
+// uses the name as their class, see `sync::lockdep`. a blocking acquisition is
+// checked before it spins or sleeps, so a deadlock is reported rather than hung on
+
+pub mod condvar;
+pub mod irq_spin_lock;
+#[cfg(debug_assertions)]
+pub mod lockdep;
+pub mod mutex;
+pub mod rwlock;
+pub mod semaphore;
+pub mod spin_lock;
+pub mod wait_queue;
+
+pub use condvar::Condvar;
+pub use irq_spin_lock::{IrqSpinLock, IrqSpinLockGuard};
+pub use mutex::{Mutex, MutexGuard};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::Semaphore;
+pub use spin_lock::{SpinLock, SpinLockGuard};
+pub use wait_queue::WaitQueue;
+
+use core::panic::Location;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LockKind {
+    Spin,
+    Sleeping,
+}
+
+// only locks with a name are validated, in debug builds, the name is their lockdep class
+#[allow(unused_variables)]
+pub(crate) fn lock_acquired(
+    name: Option<&'static str>,
+    kind: LockKind,
+    irqs_enabled: bool,
+    check: bool,
+    location: &'static Location<'static>
+) {
+    #[cfg(debug_assertions)]
+    {
+        if let Some(name) = name {
+            let kind = match kind {
+                LockKind::Spin => lockdep::Kind::Spin,
+                LockKind::Sleeping => lockdep::Kind::Sleeping
+            };
+            lockdep::acquire(name, kind, irqs_enabled, check, location);
+        }
+    }
+}
+
+#[allow(unused_variables)]
+pub(crate) fn lock_released(name: Option<&'static str>) {
+    #[cfg(debug_assertions)]
+    {
+        if let Some(name) = name {
+            lockdep::release(name);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::panic::Location;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use x86_64::instructions::interrupts;
+
+use super::{lock_acquired, lock_released, LockKind, WaitQueue};
+
+// sleeps instead of spinning while another thread holds the lock, never
+// take it in an interrupt handler
+pub struct Mutex<T> {
+    locked: AtomicBool,
+    waiters: WaitQueue,
+    data: UnsafeCell<T>,
+    name: Option<&'static str>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+    // sharing the guard shares the data, so it is only Sync when T is
+    _data: PhantomData<&'a mut T>,
+}
+
+impl<T> Mutex<T> {
+
+    pub const fn new(value: T) -> Mutex<T> {
+        return Mutex {
+            locked: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(value),
+            name: None
+        };
+    }
+
+    pub const fn named(name: &'static str, value: T) -> Mutex<T> {
+        return Mutex {
+            locked: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(value),
+            name: Some(name)
+        };
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> MutexGuard<T> {
+        lock_acquired(self.name, LockKind::Sleeping, interrupts::are_enabled(), true, Location::caller());
+        loop {
+            if let Some(guard) = self.acquire() {
+                return guard;
+            }
+            self.waiters.wait_until(|| !self.locked.load(Ordering::Relaxed));
+        }
+    }
+
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        let guard = self.acquire()?;
+        lock_acquired(self.name, LockKind::Sleeping, interrupts::are_enabled(), false, Location::caller());
+        return Some(guard);
+    }
+
+    fn acquire(&self) -> Option<MutexGuard<T>> {
+        if self.locked.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+            return Some(MutexGuard { mutex: self, _data: PhantomData });
+        }
+        return None;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        return self.locked.load(Ordering::Relaxed);
+    }
+
+    pub fn into_inner(self) -> T {
+        return self.data.into_inner();
+    }
+}
+
+impl<'a, T> MutexGuard<'a, T> {
+    pub(super) fn mutex(&self) -> &'a Mutex<T> {
+        return self.mutex;
+    }
+}
+
+impl<'a, T> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        return unsafe { &*self.mutex.data.get() };
+    }
+}
+
+impl<'a, T> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        return unsafe { &mut *self.mutex.data.get() };
+    }
+}
+
+impl<'a, T> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        lock_released(self.mutex.name);
+        self.mutex.locked.store(false, Ordering::Release);
+        self.mutex.waiters.notify_one();
+    }
+}
+
+#[test_case]
+fn test_mutex_excludes_threads() {
+    use crate::thread;
+
+    static COUNTER: Mutex<u64> = Mutex::new(0);
+    let threads: alloc::vec::Vec<_> = (0..4).map(|_| thread::spawn(|| {
+        for _ in 0..50 {
+            let mut counter = COUNTER.lock();
+            let value = *counter;
+            // give the others a chance to see the lock held
+            thread::yield_now();
+            *counter = value + 1;
+        }
+    })).collect();
+    for handle in threads {
+        handle.join();
+    }
+    assert_eq!(*COUNTER.lock(), 200);
+}
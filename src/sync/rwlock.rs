@@ -0,0 +1,166 @@
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::panic::Location;
+use core::sync::atomic::{AtomicIsize, Ordering};
+
+use x86_64::instructions::interrupts;
+
+use super::{lock_acquired, lock_released, LockKind, WaitQueue};
+
+const WRITER: isize = -1;
+
+// any number of readers or one writer, a steady stream of readers can starve writers
+pub struct RwLock<T> {
+    // the number of readers, or WRITER
+    state: AtomicIsize,
+    waiters: WaitQueue,
+    data: UnsafeCell<T>,
+    name: Option<&'static str>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T> RwLock<T> {
+
+    pub const fn new(value: T) -> RwLock<T> {
+        return RwLock {
+            state: AtomicIsize::new(0),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(value),
+            name: None
+        };
+    }
+
+    pub const fn named(name: &'static str, value: T) -> RwLock<T> {
+        return RwLock {
+            state: AtomicIsize::new(0),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(value),
+            name: Some(name)
+        };
+    }
+
+    // readers may nest, so they are recorded without being checked
+    #[track_caller]
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        lock_acquired(self.name, LockKind::Sleeping, interrupts::are_enabled(), false, Location::caller());
+        loop {
+            if let Some(guard) = self.acquire_read() {
+                return guard;
+            }
+            self.waiters.wait_until(|| self.state.load(Ordering::Relaxed) != WRITER);
+        }
+    }
+
+    #[track_caller]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+        let guard = self.acquire_read()?;
+        lock_acquired(self.name, LockKind::Sleeping, interrupts::are_enabled(), false, Location::caller());
+        return Some(guard);
+    }
+
+    fn acquire_read(&self) -> Option<RwLockReadGuard<T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        while state != WRITER {
+            match self.state.compare_exchange(state, state + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return Some(RwLockReadGuard { lock: self }),
+                Err(current) => state = current
+            }
+        }
+        return None;
+    }
+
+    #[track_caller]
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        lock_acquired(self.name, LockKind::Sleeping, interrupts::are_enabled(), true, Location::caller());
+        loop {
+            if let Some(guard) = self.acquire_write() {
+                return guard;
+            }
+            self.waiters.wait_until(|| self.state.load(Ordering::Relaxed) == 0);
+        }
+    }
+
+    #[track_caller]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<T>> {
+        let guard = self.acquire_write()?;
+        lock_acquired(self.name, LockKind::Sleeping, interrupts::are_enabled(), false, Location::caller());
+        return Some(guard);
+    }
+
+    fn acquire_write(&self) -> Option<RwLockWriteGuard<T>> {
+        if self.state.compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+            return Some(RwLockWriteGuard { lock: self });
+        }
+        return None;
+    }
+}
+
+impl<'a, T> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        return unsafe { &*self.lock.data.get() };
+    }
+}
+
+impl<'a, T> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        lock_released(self.lock.name);
+        if self.lock.state.fetch_sub(1, Ordering::Release) == 1 {
+            self.lock.waiters.notify_all();
+        }
+    }
+}
+
+impl<'a, T> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        return unsafe { &*self.lock.data.get() };
+    }
+}
+
+impl<'a, T> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        return unsafe { &mut *self.lock.data.get() };
+    }
+}
+
+impl<'a, T> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        lock_released(self.lock.name);
+        self.lock.state.store(0, Ordering::Release);
+        // both readers and writers may be waiting
+        self.lock.waiters.notify_all();
+    }
+}
+
+#[test_case]
+fn test_rwlock_readers_share_writers_exclude() {
+    use crate::thread;
+
+    static LOCK: RwLock<u64> = RwLock::new(1);
+    let first = LOCK.read();
+    let second = LOCK.read();
+    assert_eq!(*first + *second, 2);
+    assert!(LOCK.try_write().is_none());
+    let writer = thread::spawn(|| {
+        *LOCK.write() = 5;
+    });
+    thread::yield_now();
+    assert_eq!(*LOCK.try_read().unwrap(), 1);
+    drop(first);
+    drop(second);
+    writer.join();
+    assert_eq!(*LOCK.read(), 5);
+}
@@ -0,0 +1,68 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::WaitQueue;
+
+// counts available resources, acquire sleeps while there are none
+pub struct Semaphore {
+    count: AtomicUsize,
+    waiters: WaitQueue,
+}
+
+impl Semaphore {
+
+    pub const fn new(count: usize) -> Semaphore {
+        return Semaphore {
+            count: AtomicUsize::new(count),
+            waiters: WaitQueue::new()
+        };
+    }
+
+    pub fn acquire(&self) {
+        loop {
+            if self.try_acquire() {
+                return;
+            }
+            self.waiters.wait_until(|| self.count.load(Ordering::Relaxed) > 0);
+        }
+    }
+
+    pub fn try_acquire(&self) -> bool {
+        let mut count = self.count.load(Ordering::Relaxed);
+        while count > 0 {
+            match self.count.compare_exchange(count, count - 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return true,
+                Err(current) => count = current
+            }
+        }
+        return false;
+    }
+
+    // safe to call from interrupt handlers
+    pub fn release(&self) {
+        self.count.fetch_add(1, Ordering::Release);
+        self.waiters.notify_one();
+    }
+
+    pub fn available(&self) -> usize {
+        return self.count.load(Ordering::Relaxed);
+    }
+}
+
+#[test_case]
+fn test_semaphore_blocks_until_released() {
+    use crate::thread;
+
+    static ITEMS: Semaphore = Semaphore::new(0);
+    let consumer = thread::spawn(|| {
+        for _ in 0..3 {
+            ITEMS.acquire();
+        }
+    });
+    for _ in 0..3 {
+        thread::yield_now();
+        ITEMS.release();
+    }
+    consumer.join();
+    assert_eq!(ITEMS.available(), 0);
+    assert!(!ITEMS.try_acquire());
+}
@@ -0,0 +1,86 @@
+use core::ops::{Deref, DerefMut};
+use core::panic::Location;
+
+use x86_64::instructions::interrupts;
+
+use super::{lock_acquired, lock_released, LockKind};
+
+// a plain spinlock that leaves interrupts alone, named ones report the real
+// interrupt state to lockdep so one taken both in a handler and with
+// interrupts on is caught
+pub struct SpinLock<T> {
+    inner: spin::Mutex<T>,
+    name: Option<&'static str>,
+}
+
+pub struct SpinLockGuard<'a, T> {
+    guard: spin::MutexGuard<'a, T>,
+    name: Option<&'static str>,
+}
+
+impl<T> SpinLock<T> {
+
+    pub const fn new(value: T) -> SpinLock<T> {
+        return SpinLock {
+            inner: spin::Mutex::new(value),
+            name: None
+        };
+    }
+
+    pub const fn named(name: &'static str, value: T) -> SpinLock<T> {
+        return SpinLock {
+            inner: spin::Mutex::new(value),
+            name: Some(name)
+        };
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> SpinLockGuard<T> {
+        lock_acquired(self.name, LockKind::Spin, interrupts::are_enabled(), true, Location::caller());
+        return SpinLockGuard {
+            guard: self.inner.lock(),
+            name: self.name
+        };
+    }
+
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<SpinLockGuard<T>> {
+        let guard = self.inner.try_lock()?;
+        lock_acquired(self.name, LockKind::Spin, interrupts::are_enabled(), false, Location::caller());
+        return Some(SpinLockGuard { guard, name: self.name });
+    }
+}
+
+impl<'a, T> Deref for SpinLockGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        return &self.guard;
+    }
+}
+
+impl<'a, T> DerefMut for SpinLockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        return &mut self.guard;
+    }
+}
+
+impl<'a, T> Drop for SpinLockGuard<'a, T> {
+    fn drop(&mut self) {
+        // the inner guard unlocks right after this
+        lock_released(self.name);
+    }
+}
+
+#[test_case]
+fn test_spin_lock_leaves_interrupts_alone() {
+    let lock = SpinLock::new(1);
+    assert!(interrupts::are_enabled());
+    {
+        let mut value = lock.lock();
+        assert!(interrupts::are_enabled());
+        assert!(lock.try_lock().is_none());
+        *value = 2;
+    }
+    assert_eq!(*lock.try_lock().unwrap(), 2);
+}
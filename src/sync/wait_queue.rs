@@ -0,0 +1,109 @@
+use x86_64::instructions::interrupts;
+
+use super::IrqSpinLock;
+use crate::thread::{self, ThreadId};
+
+// the waiting threads are linked through the thread table, so waiting never allocates
+struct Waiters {
+    head: Option<ThreadId>,
+    tail: Option<ThreadId>,
+}
+
+// threads sleeping until some condition changes, the building block of the blocking locks
+pub struct WaitQueue {
+    waiters: IrqSpinLock<Waiters>,
+}
+
+impl WaitQueue {
+
+    pub const fn new() -> WaitQueue {
+        return WaitQueue {
+            waiters: IrqSpinLock::new(Waiters { head: None, tail: None })
+        };
+    }
+
+    // sleeps until `condition` holds, it is checked with interrupts off so a
+    // notify can't slip in between the check and going to sleep
+    pub fn wait_until(&self, mut condition: impl FnMut() -> bool) {
+        loop {
+            let done = interrupts::without_interrupts(|| {
+                if condition() {
+                    return true;
+                }
+                self.sleep_after(|| ());
+                return false;
+            });
+            if done {
+                return;
+            }
+        }
+    }
+
+    // queues the current thread, runs `release` and sleeps until notified,
+    // interrupts must be disabled
+    pub(crate) fn sleep_after(&self, release: impl FnOnce()) {
+        let current = thread::current();
+        {
+            let mut waiters = self.waiters.lock();
+            match waiters.tail {
+                Some(tail) => thread::set_wait_next(tail, Some(current)),
+                None => waiters.head = Some(current)
+            }
+            waiters.tail = Some(current);
+        }
+        release();
+        thread::block_current();
+    }
+
+    fn pop(&self) -> Option<ThreadId> {
+        let mut waiters = self.waiters.lock();
+        let head = waiters.head?;
+        waiters.head = thread::take_wait_next(head);
+        if waiters.head.is_none() {
+            waiters.tail = None;
+        }
+        return Some(head);
+    }
+
+    pub fn notify_one(&self) -> bool {
+        return match self.pop() {
+            Some(id) => {
+                thread::wake(id);
+                true
+            },
+            None => false
+        };
+    }
+
+    pub fn notify_all(&self) -> usize {
+        let mut woken = 0;
+        while self.notify_one() {
+            woken = woken + 1;
+        }
+        return woken;
+    }
+}
+
+#[test_case]
+fn test_wait_queue_wakes_all_waiters() {
+    use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+    static QUEUE: WaitQueue = WaitQueue::new();
+    static GO: AtomicBool = AtomicBool::new(false);
+    static WOKEN: AtomicU64 = AtomicU64::new(0);
+    let threads: alloc::vec::Vec<_> = (0..3).map(|_| thread::spawn(|| {
+        QUEUE.wait_until(|| GO.load(Ordering::SeqCst));
+        WOKEN.fetch_add(1, Ordering::SeqCst);
+    })).collect();
+    while threads.iter().any(|handle| thread::stats(handle.id()).unwrap().state != thread::State::Blocked) {
+        thread::yield_now();
+    }
+    assert_eq!(WOKEN.load(Ordering::SeqCst), 0);
+    GO.store(true, Ordering::SeqCst);
+    assert_eq!(QUEUE.notify_all(), 3);
+    assert!(!QUEUE.notify_one());
+    for handle in threads {
+        handle.join();
+    }
+    assert_eq!(WOKEN.load(Ordering::SeqCst), 3);
+}
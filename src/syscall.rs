@@ -0,0 +1,218 @@
+// system calls through syscall/sysret: the number goes in rax, arguments in
+// rdi, rsi, rdx, r10, r8 and r9, and rax returns the result or a negative errno
+
+use lazy_static::lazy_static;
+use spin::Mutex;
+use x86_64::VirtAddr;
+use x86_64::registers::model_specific::{Efer, EferFlags, Msr};
+
+use crate::gdt;
+use crate::smp::percpu;
+
+const STAR: u32 = 0xc000_0081;
+const LSTAR: u32 = 0xc000_0082;
+const SFMASK: u32 = 0xc000_0084;
+
+// rflags bits cleared on entry: interrupts, trap flag and direction
+const ENTRY_FLAGS_MASK: u64 = 0x200 | 0x100 | 0x400;
+
+pub const MAX_SYSCALLS: usize = 64;
+
+pub const SYS_WRITE: usize = 0;
+pub const SYS_UPTIME: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i64)]
+pub enum Errno {
+    EBADF = 9,
+    EFAULT = 14,
+    EINVAL = 22,
+    ENOSYS = 38,
+}
+
+pub type Handler = fn(&Arguments) -> Result<u64, Errno>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Arguments(pub [u64; 6]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallError {
+    AlreadyRegistered,
+    OutOfRange,
+}
+
+// what the entry stub pushed, lowest address first
+#[repr(C)]
+pub struct SyscallFrame {
+    pub number: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub r10: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub rflags: u64,
+    pub rip: u64,
+    pub rsp: u64,
+}
+
+pub const KERNEL_STACK_SIZE: usize = 4096 * 5;
+// the bootstrap processor's, the other cpus get theirs from the heap
+static mut KERNEL_STACK: [u8; KERNEL_STACK_SIZE] = [0; KERNEL_STACK_SIZE];
+
+// after swapgs the per-cpu block holds the user's rsp at gs:16 and this cpu's
+// system call stack top at gs:24
+global_asm!(r#"
+.global syscall_entry
+syscall_entry:
+    swapgs
+    movq %rsp, %gs:16
+    movq %gs:24, %rsp
+    pushq %gs:16
+    pushq %rcx
+    pushq %r11
+    pushq %rdi
+    pushq %rsi
+    pushq %rdx
+    pushq %r10
+    pushq %r8
+    pushq %r9
+    pushq %rax
+    movq %rsp, %rdi
+    call syscall_dispatch
+    addq $8, %rsp
+    popq %r9
+    popq %r8
+    popq %r10
+    popq %rdx
+    popq %rsi
+    popq %rdi
+    popq %r11
+    popq %rcx
+    popq %rsp
+    swapgs
+    sysretq
+"#);
+
+extern "C" {
+    fn syscall_entry();
+}
+
+lazy_static! {
+    static ref TABLE: Mutex<[Option<Handler>; MAX_SYSCALLS]> = {
+        let mut table: [Option<Handler>; MAX_SYSCALLS] = [None; MAX_SYSCALLS];
+        table[SYS_WRITE] = Some(sys_write);
+        table[SYS_UPTIME] = Some(sys_uptime);
+        return Mutex::new(table);
+    };
+}
+
+#[no_mangle]
+extern "C" fn syscall_dispatch(frame: &SyscallFrame) -> i64 {
+    let arguments = Arguments([frame.rdi, frame.rsi, frame.rdx, frame.r10, frame.r8, frame.r9]);
+    return match dispatch(frame.number, &arguments) {
+        Ok(value) => value as i64,
+        Err(errno) => -(errno as i64)
+    };
+}
+
+fn dispatch(number: u64, arguments: &Arguments) -> Result<u64, Errno> {
+    // copied out so a handler can register system calls itself
+    let handler = TABLE.lock().get(number as usize).copied().flatten();
+    return match handler {
+        Some(handler) => handler(arguments),
+        None => Err(Errno::ENOSYS)
+    };
+}
+
+pub fn register(number: usize, handler: Handler) -> Result<(), SyscallError> {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut table = TABLE.lock();
+        let slot = table.get_mut(number).ok_or(SyscallError::OutOfRange)?;
+        if slot.is_some() {
+            return Err(SyscallError::AlreadyRegistered);
+        }
+        *slot = Some(handler);
+        return Ok(());
+    });
+}
+
+pub fn unregister(number: usize) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        if let Some(slot) = TABLE.lock().get_mut(number) {
+            *slot = None;
+        }
+    });
+}
+
+// for the bootstrap processor, after `gdt::init`. the stub pushes an even number of
+// registers, so every cpu's stack top has to be 16 byte aligned
+pub fn init() {
+    let stack_top = (unsafe { KERNEL_STACK.as_ptr() } as u64 + KERNEL_STACK_SIZE as u64) & !0xf;
+    percpu::set_syscall_stack(VirtAddr::new(stack_top));
+    init_cpu();
+}
+
+// the msrs are per cpu, so every processor runs this once, with its stack already
+// in its per-cpu block
+pub fn init_cpu() {
+    // sysret loads cs from base + 16 and ss from base + 8, so the base is the user data selector - 8
+    let selectors = gdt::selectors();
+    let kernel_base = selectors.code_selector.0 as u64;
+    let user_base = (selectors.user_data_selector.0 as u64 & !3) - 8;
+    unsafe {
+        Msr::new(STAR).write(user_base << 48 | kernel_base << 32);
+        Msr::new(LSTAR).write(syscall_entry as u64);
+        Msr::new(SFMASK).write(ENTRY_FLAGS_MASK);
+        Efer::update(|flags| *flags |= EferFlags::SYSTEM_CALL_EXTENSIONS);
+    }
+}
+
+// user pointers must be in the lower half and mapped for ring 3
+fn user_buffer(address: u64, len: u64) -> Result<&'static [u8], Errno> {
+    // the address may be null, which a slice can't be built from even when empty
+    if len == 0 {
+        return Ok(&[]);
+    }
+    let end = address.checked_add(len).ok_or(Errno::EFAULT)?;
+    if end > 0x_0000_8000_0000_0000 {
+        return Err(Errno::EFAULT);
+    }
+    if !crate::memory::is_user_accessible(VirtAddr::new(address), len) {
+        return Err(Errno::EFAULT);
+    }
+    return Ok(unsafe { core::slice::from_raw_parts(address as *const u8, len as usize) });
+}
+
+// write(fd, buffer, len), 1 and 2 both go to the console
+fn sys_write(arguments: &Arguments) -> Result<u64, Errno> {
+    let [fd, buffer, len, ..] = arguments.0;
+    if fd != 1 && fd != 2 {
+        return Err(Errno::EBADF);
+    }
+    let bytes = user_buffer(buffer, len)?;
+    let text = core::str::from_utf8(bytes).map_err(|_| Errno::EINVAL)?;
+    crate::print!("{}", text);
+    return Ok(len);
+}
+
+fn sys_uptime(_arguments: &Arguments) -> Result<u64, Errno> {
+    return Ok(crate::interrupts::uptime_ms());
+}
+
+#[test_case]
+fn test_dispatch_table() {
+    fn answer(arguments: &Arguments) -> Result<u64, Errno> {
+        return Ok(arguments.0[0] + arguments.0[5]);
+    }
+    let arguments = Arguments([40, 0, 0, 0, 0, 2]);
+    assert_eq!(dispatch(MAX_SYSCALLS as u64 - 1, &arguments), Err(Errno::ENOSYS));
+    assert_eq!(dispatch(u64::max_value(), &arguments), Err(Errno::ENOSYS));
+    register(MAX_SYSCALLS - 1, answer).unwrap();
+    assert_eq!(register(MAX_SYSCALLS - 1, answer), Err(SyscallError::AlreadyRegistered));
+    assert_eq!(register(MAX_SYSCALLS, answer), Err(SyscallError::OutOfRange));
+    assert_eq!(dispatch(MAX_SYSCALLS as u64 - 1, &arguments), Ok(42));
+    unregister(MAX_SYSCALLS - 1);
+    assert_eq!(dispatch(SYS_WRITE as u64, &Arguments([5, 0, 0, 0, 0, 0])), Err(Errno::EBADF));
+    assert_eq!(dispatch(SYS_WRITE as u64, &Arguments([1, 0, 0, 0, 0, 0])), Ok(0));
+}
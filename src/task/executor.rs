@@ -0,0 +1,185 @@
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+use spin::Mutex;
+
+use super::{Task, TaskId};
+
+// wakers run in interrupt handlers, so the queue never grows past this
+const TASK_QUEUE_CAPACITY: usize = 128;
+
+type TaskQueue = Mutex<VecDeque<TaskId>>;
+
+// polls only the tasks that were woken and halts the cpu when none are
+pub struct Executor {
+    tasks: BTreeMap<TaskId, Task>,
+    task_queue: Arc<TaskQueue>,
+    waker_cache: BTreeMap<TaskId, Waker>,
+}
+
+impl Executor {
+
+    pub fn new() -> Executor {
+        return Executor {
+            tasks: BTreeMap::new(),
+            task_queue: Arc::new(Mutex::new(VecDeque::with_capacity(TASK_QUEUE_CAPACITY))),
+            waker_cache: BTreeMap::new()
+        };
+    }
+
+    pub fn spawn(&mut self, task: Task) {
+        let id = task.id;
+        if self.tasks.insert(id, task).is_some() {
+            panic!("task with same id already in tasks");
+        }
+        if !push(&self.task_queue, id) {
+            panic!("task queue full");
+        }
+    }
+
+    pub fn run(&mut self) -> ! {
+        loop {
+            self.run_ready_tasks();
+            self.sleep_if_idle();
+        }
+    }
+
+    // polls until no task is ready, returns how many are still pending
+    pub fn run_until_stalled(&mut self) -> usize {
+        self.run_ready_tasks();
+        return self.tasks.len();
+    }
+
+    fn run_ready_tasks(&mut self) {
+        while let Some(id) = pop(&self.task_queue) {
+            let task = match self.tasks.get_mut(&id) {
+                Some(task) => task,
+                // woken after it finished
+                None => continue
+            };
+            let task_queue = &self.task_queue;
+            let waker = self.waker_cache
+                .entry(id)
+                .or_insert_with(|| TaskWaker::new(id, task_queue.clone()));
+            let mut context = Context::from_waker(waker);
+            if let Poll::Ready(()) = task.poll(&mut context) {
+                self.tasks.remove(&id);
+                self.waker_cache.remove(&id);
+            }
+        }
+    }
+
+    fn sleep_if_idle(&self) {
+        use x86_64::instructions::interrupts;
+
+        // an interrupt between the check and hlt would otherwise be missed
+        interrupts::disable();
+        if self.task_queue.lock().is_empty() {
+            interrupts::enable_and_hlt();
+        } else {
+            interrupts::enable();
+        }
+    }
+}
+
+fn push(task_queue: &TaskQueue, id: TaskId) -> bool {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut queue = task_queue.lock();
+        if queue.len() == TASK_QUEUE_CAPACITY {
+            return false;
+        }
+        queue.push_back(id);
+        return true;
+    });
+}
+
+fn pop(task_queue: &TaskQueue) -> Option<TaskId> {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        return task_queue.lock().pop_front();
+    });
+}
+
+struct TaskWaker {
+    id: TaskId,
+    task_queue: Arc<TaskQueue>,
+}
+
+impl TaskWaker {
+
+    fn new(id: TaskId, task_queue: Arc<TaskQueue>) -> Waker {
+        let waker = Arc::new(TaskWaker { id, task_queue });
+        return unsafe { Waker::from_raw(raw_waker(Arc::into_raw(waker))) };
+    }
+
+    // may run in an interrupt handler, so a full queue just loses the wake up
+    fn wake_task(&self) {
+        push(&self.task_queue, self.id);
+    }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone_waker, wake, wake_by_ref, drop_waker);
+
+fn raw_waker(waker: *const TaskWaker) -> RawWaker {
+    return RawWaker::new(waker as *const (), &VTABLE);
+}
+
+fn clone_waker(data: *const ()) -> RawWaker {
+    let waker = unsafe { Arc::from_raw(data as *const TaskWaker) };
+    let clone = waker.clone();
+    core::mem::forget(waker);
+    return raw_waker(Arc::into_raw(clone));
+}
+
+fn wake(data: *const ()) {
+    let waker = unsafe { Arc::from_raw(data as *const TaskWaker) };
+    waker.wake_task();
+}
+
+fn wake_by_ref(data: *const ()) {
+    let waker = unsafe { &*(data as *const TaskWaker) };
+    waker.wake_task();
+}
+
+fn drop_waker(data: *const ()) {
+    unsafe { drop(Arc::from_raw(data as *const TaskWaker)) };
+}
+
+#[test_case]
+fn test_executor_polls_woken_tasks() {
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    let order = Rc::new(Cell::new(0u32));
+    let mut executor = Executor::new();
+    for digit in 1..=2 {
+        let order = order.clone();
+        executor.spawn(Task::new(async move {
+            order.set(order.get() * 10 + digit);
+            super::yield_now().await;
+            order.set(order.get() * 10 + digit);
+        }));
+    }
+    assert_eq!(executor.run_until_stalled(), 0);
+    assert_eq!(order.get(), 1212);
+}
+
+#[test_case]
+fn test_executor_keeps_unwoken_tasks() {
+    use core::future::Future;
+    use core::pin::Pin;
+
+    struct Never;
+
+    impl Future for Never {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, _context: &mut Context) -> Poll<()> {
+            return Poll::Pending;
+        }
+    }
+
+    let mut executor = Executor::new();
+    executor.spawn(Task::new(Never));
+    assert_eq!(executor.run_until_stalled(), 1);
+}
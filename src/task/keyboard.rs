@@ -0,0 +1,90 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll};
+
+use spin::Mutex;
+
+use crate::serial::ring_buffer::RingBuffer;
+use super::waker::AtomicWaker;
+
+// scancodes are only queued while a stream exists
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static SCANCODES: Mutex<RingBuffer> = Mutex::new(RingBuffer::new());
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+// called by the keyboard interrupt handler
+pub(crate) fn add_scancode(scancode: u8) {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    let queued = match SCANCODES.try_lock() {
+        Some(mut scancodes) => scancodes.push(scancode),
+        None => false
+    };
+    if queued {
+        WAKER.wake();
+    }
+}
+
+// raw scancodes from the keyboard, only one stream can exist at a time
+pub struct ScancodeStream {
+    _private: (),
+}
+
+impl ScancodeStream {
+
+    pub fn new() -> ScancodeStream {
+        if ACTIVE.compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed).is_err() {
+            panic!("ScancodeStream::new should only be called once");
+        }
+        return ScancodeStream { _private: () };
+    }
+
+    pub fn poll_next(&mut self, context: &mut Context) -> Poll<Option<u8>> {
+        if let Some(scancode) = pop() {
+            return Poll::Ready(Some(scancode));
+        }
+        WAKER.register(context.waker());
+        // the interrupt may have queued one before the waker was registered
+        return match pop() {
+            Some(scancode) => Poll::Ready(Some(scancode)),
+            None => Poll::Pending
+        };
+    }
+
+    pub fn next(&mut self) -> Next<'_> {
+        return Next { stream: self };
+    }
+}
+
+impl Drop for ScancodeStream {
+    fn drop(&mut self) {
+        ACTIVE.store(false, Ordering::Relaxed);
+    }
+}
+
+fn pop() -> Option<u8> {
+    return x86_64::instructions::interrupts::without_interrupts(|| {
+        return SCANCODES.lock().pop();
+    });
+}
+
+pub struct Next<'a> {
+    stream: &'a mut ScancodeStream,
+}
+
+impl<'a> Future for Next<'a> {
+    type Output = Option<u8>;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<Option<u8>> {
+        return self.stream.poll_next(context);
+    }
+}
+
+pub async fn trace_scancodes() {
+    let mut scancodes = ScancodeStream::new();
+    while let Some(scancode) = scancodes.next().await {
+        crate::trace!("scancode {:#04x}", scancode);
+    }
+}
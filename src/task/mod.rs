@@ -0,0 +1,67 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll};
+
+use alloc::boxed::Box;
+
+pub mod executor;
+pub mod keyboard;
+pub mod simple_executor;
+pub mod timer;
+pub mod waker;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    fn new() -> TaskId {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        return TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    }
+}
+
+pub struct Task {
+    id: TaskId,
+    future: Pin<Box<dyn Future<Output = ()>>>,
+}
+
+impl Task {
+
+    pub fn new(future: impl Future<Output = ()> + 'static) -> Task {
+        return Task {
+            id: TaskId::new(),
+            future: Box::pin(future)
+        };
+    }
+
+    pub fn id(&self) -> TaskId {
+        return self.id;
+    }
+
+    fn poll(&mut self, context: &mut Context) -> Poll<()> {
+        return self.future.as_mut().poll(context);
+    }
+}
+
+// gives the other tasks a turn, the current one is woken straight away
+pub fn yield_now() -> YieldNow {
+    return YieldNow { yielded: false };
+}
+
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        context.waker().wake_by_ref();
+        return Poll::Pending;
+    }
+}
@@ -0,0 +1,65 @@
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use alloc::collections::VecDeque;
+
+use super::Task;
+
+// polls every task in turn until all of them finish, wakers are ignored
+pub struct SimpleExecutor {
+    task_queue: VecDeque<Task>,
+}
+
+impl SimpleExecutor {
+
+    pub fn new() -> SimpleExecutor {
+        return SimpleExecutor {
+            task_queue: VecDeque::new()
+        };
+    }
+
+    pub fn spawn(&mut self, task: Task) {
+        self.task_queue.push_back(task);
+    }
+
+    pub fn run(&mut self) {
+        let waker = dummy_waker();
+        let mut context = Context::from_waker(&waker);
+        while let Some(mut task) = self.task_queue.pop_front() {
+            if let Poll::Pending = task.poll(&mut context) {
+                self.task_queue.push_back(task);
+            }
+        }
+    }
+}
+
+fn dummy_raw_waker() -> RawWaker {
+    fn no_op(_: *const ()) {}
+    fn clone(_: *const ()) -> RawWaker {
+        return dummy_raw_waker();
+    }
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+    return RawWaker::new(0 as *const (), &VTABLE);
+}
+
+fn dummy_waker() -> Waker {
+    return unsafe { Waker::from_raw(dummy_raw_waker()) };
+}
+
+#[test_case]
+fn test_simple_executor_runs_to_completion() {
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    let count = Rc::new(Cell::new(0));
+    let mut executor = SimpleExecutor::new();
+    for _ in 0..3 {
+        let count = count.clone();
+        executor.spawn(Task::new(async move {
+            super::yield_now().await;
+            count.set(count.get() + 1);
+        }));
+    }
+    executor.run();
+    assert_eq!(count.get(), 3);
+}
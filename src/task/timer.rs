@@ -0,0 +1,89 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use alloc::vec::Vec;
+
+use crate::interrupts;
+use crate::sync::SpinLock;
+
+struct Sleeper {
+    id: u64,
+    deadline: u64,
+    waker: Waker,
+}
+
+static SLEEPERS: SpinLock<Vec<Sleeper>> = SpinLock::named("sleepers", Vec::new());
+
+// called by the timer interrupt handler, entries are removed by their futures
+// so nothing is freed in interrupt context
+pub(crate) fn tick(now: u64) {
+    if let Some(sleepers) = SLEEPERS.try_lock() {
+        for sleeper in sleepers.iter().filter(|sleeper| sleeper.deadline <= now) {
+            sleeper.waker.wake_by_ref();
+        }
+    }
+}
+
+// completes once at least `ms` milliseconds have passed
+pub fn sleep(ms: u64) -> Sleep {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    return Sleep {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        // the current tick is already partly over
+        deadline: interrupts::ticks() + interrupts::ms_to_ticks(ms) + 1
+    };
+}
+
+pub struct Sleep {
+    id: u64,
+    deadline: u64,
+}
+
+impl Sleep {
+    fn remove(&self) {
+        x86_64::instructions::interrupts::without_interrupts(|| {
+            SLEEPERS.lock().retain(|sleeper| sleeper.id != self.id);
+        });
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        if interrupts::ticks() >= self.deadline {
+            self.remove();
+            return Poll::Ready(());
+        }
+        let waker = context.waker().clone();
+        x86_64::instructions::interrupts::without_interrupts(|| {
+            let mut sleepers = SLEEPERS.lock();
+            match sleepers.iter_mut().find(|sleeper| sleeper.id == self.id) {
+                Some(sleeper) => sleeper.waker = waker,
+                None => sleepers.push(Sleeper { id: self.id, deadline: self.deadline, waker })
+            }
+        });
+        return Poll::Pending;
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+#[test_case]
+fn test_sleep_wakes_after_deadline() {
+    use super::{executor::Executor, Task};
+
+    let start = interrupts::uptime_ms();
+    let mut executor = Executor::new();
+    executor.spawn(Task::new(sleep(20)));
+    while executor.run_until_stalled() > 0 {
+        x86_64::instructions::hlt();
+    }
+    assert!(interrupts::uptime_ms() - start >= 20);
+}
@@ -0,0 +1,39 @@
+use core::task::Waker;
+
+use spin::Mutex;
+
+// a waker slot shared between a future and the interrupt handler feeding it
+pub struct AtomicWaker {
+    waker: Mutex<Option<Waker>>,
+}
+
+impl AtomicWaker {
+
+    pub const fn new() -> AtomicWaker {
+        return AtomicWaker {
+            waker: Mutex::new(None)
+        };
+    }
+
+    pub fn register(&self, waker: &Waker) {
+        x86_64::instructions::interrupts::without_interrupts(|| {
+            let mut slot = self.waker.lock();
+            let stale = match slot.as_ref() {
+                Some(registered) => !registered.will_wake(waker),
+                None => true
+            };
+            if stale {
+                *slot = Some(waker.clone());
+            }
+        });
+    }
+
+    // safe from interrupt handlers, the waker is never dropped here so nothing is freed
+    pub fn wake(&self) {
+        if let Some(slot) = self.waker.try_lock() {
+            if let Some(waker) = slot.as_ref() {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+pub const STACK_SIZE: usize = 4096 * 4;
+
+// interrupts stay off until thread_entry, the switch always happens with them off
+const INITIAL_RFLAGS: u64 = 0x2;
+
+global_asm!(r#"
+.global switch_context
+// rdi = where to save the old stack pointer, rsi = the new stack pointer
+switch_context:
+    pushq %rbp
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    pushq %r14
+    pushq %r15
+    pushfq
+    movq %rsp, (%rdi)
+    movq %rsi, %rsp
+    popfq
+    popq %r15
+    popq %r14
+    popq %r13
+    popq %r12
+    popq %rbx
+    popq %rbp
+    ret
+
+.global thread_start
+// a new thread's first switch returns here with its closure in r15
+thread_start:
+    movq %r15, %rdi
+    call thread_entry
+    ud2
+"#);
+
+extern "C" {
+    pub fn switch_context(old_rsp: *mut u64, new_rsp: u64);
+    fn thread_start();
+}
+
+pub struct Stack {
+    memory: Vec<u8>,
+}
+
+impl Stack {
+
+    pub fn new() -> Stack {
+        return Stack {
+            memory: alloc::vec![0; STACK_SIZE]
+        };
+    }
+
+    // aligned so thread_start calls into rust with the stack the abi expects
+    fn top(&mut self) -> u64 {
+        let end = self.memory.as_mut_ptr() as u64 + STACK_SIZE as u64;
+        return end & !0xf;
+    }
+
+    // lays out a frame that switch_context "returns" into thread_start from
+    pub fn prepare(&mut self, entry: Box<Box<dyn FnOnce()>>) -> u64 {
+        // what switch_context pops: rflags, r15, r14, r13, r12, rbx, rbp and the return address
+        let frame: [u64; 8] = [INITIAL_RFLAGS, Box::into_raw(entry) as u64, 0, 0, 0, 0, 0, thread_start as u64];
+        let frame_address = self.top() - core::mem::size_of_val(&frame) as u64;
+        unsafe { (frame_address as *mut [u64; 8]).write(frame) };
+        return frame_address;
+    }
+}
+
+#[no_mangle]
+extern "C" fn thread_entry(entry: *mut Box<dyn FnOnce()>) -> ! {
+    let entry = unsafe { Box::from_raw(entry) };
+    x86_64::instructions::interrupts::enable();
+    entry();
+    super::exit();
+}
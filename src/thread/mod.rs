@@ -0,0 +1,551 @@
+// preemptive kernel threads, the timer tick asks the scheduling policy whether to switch
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::{Mutex, MutexGuard};
+use x86_64::instructions::interrupts;
+
+pub mod context;
+pub mod scheduler;
+
+use context::Stack;
+use scheduler::{Entity, Policy, Scheduler, MAX_NICE, MIN_NICE};
+
+pub const MAX_THREADS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ThreadId(u64);
+
+impl ThreadId {
+    // the flow that called init, it runs on the boot stack
+    pub const MAIN: ThreadId = ThreadId(0);
+    const IDLE: ThreadId = ThreadId(1);
+
+    fn new() -> ThreadId {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(2);
+        return ThreadId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Ready,
+    Running,
+    Sleeping(u64),
+    Blocked,
+    Exited,
+}
+
+struct Thread {
+    id: ThreadId,
+    state: State,
+    rsp: u64,
+    // none for the main thread
+    stack: Option<Stack>,
+    joiner: Option<ThreadId>,
+    detached: bool,
+    entity: Entity,
+    // the next thread in the wait queue this one sleeps on
+    wait_next: Option<ThreadId>,
+}
+
+impl Thread {
+    fn new(id: ThreadId, stack: Option<Stack>) -> Thread {
+        return Thread {
+            id,
+            state: State::Ready,
+            rsp: 0,
+            stack,
+            joiner: None,
+            detached: false,
+            entity: Entity::new(id),
+            wait_next: None
+        };
+    }
+}
+
+// threads are boxed before the lock is taken and freed after it is released,
+// a preempted thread may be holding the allocator
+struct Threads {
+    // allocated once with MAX_THREADS entries
+    slots: Vec<Option<Box<Thread>>>,
+    current: ThreadId,
+    scheduler: Box<dyn Scheduler>,
+}
+
+fn find(slots: &mut [Option<Box<Thread>>], id: ThreadId) -> Option<&mut Thread> {
+    return slots.iter_mut()
+        .filter_map(|slot| slot.as_mut())
+        .find(|thread| thread.id == id)
+        .map(|thread| &mut **thread);
+}
+
+impl Threads {
+
+    fn get(&mut self, id: ThreadId) -> Option<&mut Thread> {
+        return find(&mut self.slots, id);
+    }
+
+    fn current(&mut self) -> &mut Thread {
+        let id = self.current;
+        return self.get(id).expect("current thread missing");
+    }
+
+    fn insert(&mut self, thread: Box<Thread>) -> Result<(), Box<Thread>> {
+        return match self.slots.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(thread);
+                Ok(())
+            },
+            None => Err(thread)
+        };
+    }
+
+    fn remove(&mut self, id: ThreadId) -> Option<Box<Thread>> {
+        let slot = self.slots.iter_mut().find(|slot| match slot {
+            Some(thread) => thread.id == id,
+            None => false
+        })?;
+        return slot.take();
+    }
+
+    fn make_ready(&mut self, id: ThreadId) {
+        if let Some(thread) = find(&mut self.slots, id) {
+            thread.state = State::Ready;
+            self.scheduler.enqueue(&mut thread.entity);
+        }
+    }
+
+    fn wake_sleepers(&mut self, now: u64) {
+        for thread in self.slots.iter_mut().filter_map(|slot| slot.as_mut()) {
+            if let State::Sleeping(deadline) = thread.state {
+                if deadline <= now {
+                    thread.state = State::Ready;
+                    self.scheduler.enqueue(&mut thread.entity);
+                }
+            }
+        }
+    }
+
+    // charges the tick to the running thread, true when the policy wants to switch
+    fn tick(&mut self) -> bool {
+        if self.current == ThreadId::IDLE {
+            return true;
+        }
+        let current = self.current;
+        let thread = find(&mut self.slots, current).expect("current thread missing");
+        thread.entity.runtime = thread.entity.runtime + 1;
+        thread.entity.slice = thread.entity.slice + 1;
+        return self.scheduler.tick(&mut thread.entity);
+    }
+
+    // picks the next thread, returns where to save the current stack pointer and the one to load
+    fn switch(&mut self) -> Option<(*mut u64, u64)> {
+        let current = self.current;
+        let thread = find(&mut self.slots, current).expect("current thread missing");
+        // the idle thread only runs when the policy has nothing else
+        if current != ThreadId::IDLE && thread.state == State::Running {
+            thread.state = State::Ready;
+            self.scheduler.enqueue(&mut thread.entity);
+        }
+        let next = self.scheduler.pick_next().unwrap_or(ThreadId::IDLE);
+        if next == current {
+            thread.state = State::Running;
+            return None;
+        }
+        if current == ThreadId::IDLE {
+            thread.state = State::Ready;
+        }
+        let old_rsp: *mut u64 = &mut thread.rsp;
+        self.current = next;
+        let thread = self.current();
+        thread.state = State::Running;
+        return Some((old_rsp, thread.rsp));
+    }
+
+    // detached threads that finished, nobody will join them
+    fn take_reapable(&mut self) -> Option<Box<Thread>> {
+        let current = self.current;
+        let slot = self.slots.iter_mut().find(|slot| match slot {
+            Some(thread) => thread.detached && thread.state == State::Exited && thread.id != current,
+            None => false
+        })?;
+        return slot.take();
+    }
+}
+
+static THREADS: Mutex<Option<Threads>> = Mutex::new(None);
+
+// must be called with interrupts disabled, returns once this thread runs again
+fn reschedule(mut threads: MutexGuard<Option<Threads>>) {
+    let switch = match threads.as_mut() {
+        Some(threads) => threads.switch(),
+        None => None
+    };
+    drop(threads);
+    if let Some((old_rsp, new_rsp)) = switch {
+        // the thread table keeps each thread boxed, so old_rsp stays valid
+        unsafe { context::switch_context(old_rsp, new_rsp) };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadError {
+    NoSuchThread,
+    InvalidNice,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadStats {
+    pub id: ThreadId,
+    pub state: State,
+    pub nice: i8,
+    pub cpu_time_ms: u64,
+}
+
+// turns the running flow into the main thread, needs the heap
+pub fn init() {
+    init_with(Policy::DEFAULT);
+}
+
+pub fn init_with(policy: Policy) {
+    let mut idle_stack = Stack::new();
+    let idle_rsp = idle_stack.prepare(Box::new(Box::new(idle)));
+    let mut main = Box::new(Thread::new(ThreadId::MAIN, None));
+    main.state = State::Running;
+    let mut idle_thread = Box::new(Thread::new(ThreadId::IDLE, Some(idle_stack)));
+    idle_thread.rsp = idle_rsp;
+    let mut threads = Threads {
+        slots: (0..MAX_THREADS).map(|_| None).collect(),
+        current: ThreadId::MAIN,
+        scheduler: policy.build()
+    };
+    threads.slots[0] = Some(main);
+    threads.slots[1] = Some(idle_thread);
+    interrupts::without_interrupts(|| {
+        *THREADS.lock() = Some(threads);
+    });
+}
+
+fn idle() {
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+pub fn is_initialized() -> bool {
+    return interrupts::without_interrupts(|| {
+        return THREADS.lock().is_some();
+    });
+}
+
+pub fn current() -> ThreadId {
+    return interrupts::without_interrupts(|| {
+        return match THREADS.lock().as_ref() {
+            Some(threads) => threads.current,
+            None => ThreadId::MAIN
+        };
+    });
+}
+
+pub struct JoinHandle<T> {
+    id: ThreadId,
+    result: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+
+    pub fn id(&self) -> ThreadId {
+        return self.id;
+    }
+
+    // blocks until the thread exits and returns what its closure returned
+    pub fn join(self) -> T {
+        wait_for_exit(self.id);
+        let result = self.result.lock().take();
+        return result.expect("joined thread exited without a result");
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        let id = self.id;
+        let exited = interrupts::without_interrupts(|| {
+            let mut threads = THREADS.lock();
+            let threads = threads.as_mut()?;
+            let thread = threads.get(id)?;
+            thread.detached = true;
+            if thread.state == State::Exited {
+                return threads.remove(id);
+            }
+            return None;
+        });
+        drop(exited);
+    }
+}
+
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static
+{
+    reap();
+    let result = Arc::new(Mutex::new(None));
+    let packet = result.clone();
+    let entry: Box<Box<dyn FnOnce()>> = Box::new(Box::new(move || {
+        let value = f();
+        *packet.lock() = Some(value);
+    }));
+    let mut stack = Stack::new();
+    let rsp = stack.prepare(entry);
+    let id = ThreadId::new();
+    let mut thread = Box::new(Thread::new(id, Some(stack)));
+    thread.rsp = rsp;
+
+    interrupts::without_interrupts(|| {
+        let mut threads = THREADS.lock();
+        let threads = threads.as_mut().expect("threads not initialized");
+        if threads.insert(thread).is_err() {
+            panic!("too many threads");
+        }
+        threads.make_ready(id);
+    });
+    return JoinHandle { id, result };
+}
+
+fn reap() {
+    loop {
+        let thread = interrupts::without_interrupts(|| {
+            return THREADS.lock().as_mut().and_then(|threads| threads.take_reapable());
+        });
+        match thread {
+            Some(thread) => drop(thread),
+            None => return
+        }
+    }
+}
+
+fn wait_for_exit(id: ThreadId) {
+    let thread = interrupts::without_interrupts(|| {
+        loop {
+            let mut guard = THREADS.lock();
+            let threads = guard.as_mut().expect("threads not initialized");
+            let current = threads.current;
+            let thread = threads.get(id).expect("no such thread");
+            if thread.state == State::Exited {
+                return threads.remove(id);
+            }
+            thread.joiner = Some(current);
+            threads.current().state = State::Blocked;
+            reschedule(guard);
+        }
+    });
+    drop(thread);
+}
+
+// lets the other ready threads run first
+pub fn yield_now() {
+    interrupts::without_interrupts(|| {
+        reschedule(THREADS.lock());
+    });
+}
+
+pub fn sleep(ms: u64) {
+    let deadline = crate::interrupts::ticks() + crate::interrupts::ms_to_ticks(ms) + 1;
+    interrupts::without_interrupts(|| {
+        let mut guard = THREADS.lock();
+        if let Some(threads) = guard.as_mut() {
+            threads.current().state = State::Sleeping(deadline);
+        }
+        reschedule(guard);
+    });
+}
+
+pub fn exit() -> ! {
+    interrupts::disable();
+    let mut guard = THREADS.lock();
+    let threads = guard.as_mut().expect("threads not initialized");
+    let thread = threads.current();
+    thread.state = State::Exited;
+    if let Some(joiner) = thread.joiner.take() {
+        threads.make_ready(joiner);
+    }
+    reschedule(guard);
+    unreachable!("exited thread was scheduled again");
+}
+
+// sleeps until `wake` is called for this thread, interrupts must be disabled
+pub(crate) fn block_current() {
+    let mut guard = THREADS.lock();
+    if let Some(threads) = guard.as_mut() {
+        threads.current().state = State::Blocked;
+    }
+    reschedule(guard);
+}
+
+// readies a thread put to sleep by `block_current`, returns false if it wasn't blocked
+pub(crate) fn wake(id: ThreadId) -> bool {
+    return interrupts::without_interrupts(|| {
+        let mut guard = THREADS.lock();
+        let threads = match guard.as_mut() {
+            Some(threads) => threads,
+            None => return false
+        };
+        let blocked = threads.get(id).map_or(false, |thread| thread.state == State::Blocked);
+        if blocked {
+            threads.make_ready(id);
+        }
+        return blocked;
+    });
+}
+
+pub(crate) fn set_wait_next(id: ThreadId, next: Option<ThreadId>) {
+    interrupts::without_interrupts(|| {
+        if let Some(thread) = THREADS.lock().as_mut().and_then(|threads| threads.get(id)) {
+            thread.wait_next = next;
+        }
+    });
+}
+
+pub(crate) fn take_wait_next(id: ThreadId) -> Option<ThreadId> {
+    return interrupts::without_interrupts(|| {
+        return THREADS.lock().as_mut().and_then(|threads| threads.get(id)).and_then(|thread| thread.wait_next.take());
+    });
+}
+
+// called by the timer interrupt handler after the eoi
+pub(crate) fn tick(now: u64) {
+    if let Some(mut guard) = THREADS.try_lock() {
+        let switch = match guard.as_mut() {
+            Some(threads) => {
+                threads.wake_sleepers(now);
+                threads.tick()
+            },
+            None => false
+        };
+        if switch {
+            reschedule(guard);
+        }
+    }
+}
+
+pub fn policy_name() -> &'static str {
+    return interrupts::without_interrupts(|| {
+        return match THREADS.lock().as_ref() {
+            Some(threads) => threads.scheduler.name(),
+            None => "none"
+        };
+    });
+}
+
+// takes effect the next time the thread is charged or queued
+pub fn set_nice(id: ThreadId, nice: i8) -> Result<(), ThreadError> {
+    if nice < MIN_NICE || nice > MAX_NICE {
+        return Err(ThreadError::InvalidNice);
+    }
+    return interrupts::without_interrupts(|| {
+        let mut threads = THREADS.lock();
+        let thread = threads.as_mut().and_then(|threads| threads.get(id)).ok_or(ThreadError::NoSuchThread)?;
+        thread.entity.nice = nice;
+        return Ok(());
+    });
+}
+
+pub fn stats(id: ThreadId) -> Result<ThreadStats, ThreadError> {
+    return interrupts::without_interrupts(|| {
+        let mut threads = THREADS.lock();
+        let thread = threads.as_mut().and_then(|threads| threads.get(id)).ok_or(ThreadError::NoSuchThread)?;
+        return Ok(ThreadStats {
+            id,
+            state: thread.state,
+            nice: thread.entity.nice,
+            cpu_time_ms: crate::interrupts::ticks_to_ms(thread.entity.runtime)
+        });
+    });
+}
+
+// every live thread, collected under the lock and handed out after it
+pub fn all_stats() -> [Option<ThreadStats>; MAX_THREADS] {
+    let mut all = [None; MAX_THREADS];
+    interrupts::without_interrupts(|| {
+        if let Some(threads) = THREADS.lock().as_ref() {
+            let live = threads.slots.iter().filter_map(|slot| slot.as_ref());
+            for (stats, thread) in all.iter_mut().zip(live) {
+                *stats = Some(ThreadStats {
+                    id: thread.id,
+                    state: thread.state,
+                    nice: thread.entity.nice,
+                    cpu_time_ms: crate::interrupts::ticks_to_ms(thread.entity.runtime)
+                });
+            }
+        }
+    });
+    return all;
+}
+
+#[test_case]
+fn test_join_returns_result() {
+    let handle = spawn(|| 6 * 7);
+    assert_eq!(handle.join(), 42);
+}
+
+#[test_case]
+fn test_threads_are_preempted() {
+    use core::sync::atomic::AtomicBool;
+
+    // the spinner never yields, the setter only runs if the timer switches away from it
+    static FLAG: AtomicBool = AtomicBool::new(false);
+    let spinner = spawn(|| {
+        while !FLAG.load(Ordering::SeqCst) {
+            core::sync::atomic::spin_loop_hint();
+        }
+    });
+    let setter = spawn(|| FLAG.store(true, Ordering::SeqCst));
+    spinner.join();
+    setter.join();
+}
+
+#[test_case]
+fn test_sleep_lets_others_run() {
+    use core::sync::atomic::AtomicU64;
+
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+    let start = crate::interrupts::uptime_ms();
+    let sleeper = spawn(move || {
+        sleep(100);
+        return COUNT.load(Ordering::SeqCst);
+    });
+    let counter = spawn(|| {
+        for _ in 0..3 {
+            COUNT.fetch_add(1, Ordering::SeqCst);
+            yield_now();
+        }
+    });
+    counter.join();
+    assert_eq!(sleeper.join(), 3);
+    assert!(crate::interrupts::uptime_ms() - start >= 100);
+}
+
+#[test_case]
+fn test_cpu_time_is_accounted() {
+    let busy = spawn(|| {
+        let start = crate::interrupts::ticks();
+        while crate::interrupts::ticks() < start + 4 {
+            core::sync::atomic::spin_loop_hint();
+        }
+    });
+    let id = busy.id();
+    assert_eq!(set_nice(id, 5), Ok(()));
+    assert_eq!(set_nice(id, 20), Err(ThreadError::InvalidNice));
+    // the thread stays in the table until it is joined
+    while stats(id).unwrap().state != State::Exited {
+        yield_now();
+    }
+    let stats = stats(id).unwrap();
+    assert_eq!(stats.nice, 5);
+    assert!(stats.cpu_time_ms > 0);
+    busy.join();
+}
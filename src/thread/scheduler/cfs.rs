@@ -0,0 +1,90 @@
+use alloc::vec::Vec;
+
+use super::{nice_weight, Entity, Scheduler, NICE_0_WEIGHT};
+use crate::thread::{ThreadId, MAX_THREADS};
+
+// how far ahead of the most deserving waiting thread the running one may get
+const GRANULARITY: u64 = NICE_0_WEIGHT;
+
+// completely fair: runs the thread with the smallest virtual runtime, which
+// grows slower for threads with a lower nice value
+pub struct Cfs {
+    // (vruntime, id), searched linearly, there are at most MAX_THREADS
+    ready: Vec<(u64, ThreadId)>,
+    min_vruntime: u64,
+}
+
+impl Cfs {
+
+    pub fn new() -> Cfs {
+        return Cfs {
+            ready: Vec::with_capacity(MAX_THREADS),
+            min_vruntime: 0
+        };
+    }
+
+    fn leftmost(&self) -> Option<usize> {
+        return self.ready.iter()
+            .enumerate()
+            .min_by_key(|(_, (vruntime, _))| *vruntime)
+            .map(|(index, _)| index);
+    }
+}
+
+impl Scheduler for Cfs {
+
+    fn name(&self) -> &'static str {
+        return "cfs";
+    }
+
+    fn enqueue(&mut self, entity: &mut Entity) {
+        // sleepers don't get to bank the time they were away
+        entity.vruntime = entity.vruntime.max(self.min_vruntime);
+        self.ready.push((entity.vruntime, entity.id));
+    }
+
+    fn pick_next(&mut self) -> Option<ThreadId> {
+        let index = self.leftmost()?;
+        let (vruntime, id) = self.ready.swap_remove(index);
+        self.min_vruntime = self.min_vruntime.max(vruntime);
+        return Some(id);
+    }
+
+    fn tick(&mut self, entity: &mut Entity) -> bool {
+        entity.vruntime = entity.vruntime + NICE_0_WEIGHT * NICE_0_WEIGHT / nice_weight(entity.nice);
+        return match self.leftmost() {
+            Some(index) => entity.vruntime > self.ready[index].0 + GRANULARITY,
+            None => false
+        };
+    }
+}
+
+#[test_case]
+fn test_cfs_weights_by_nice() {
+    let mut scheduler = Cfs::new();
+    let mut high = Entity::new(ThreadId(20));
+    let mut low = Entity::new(ThreadId(21));
+    high.nice = -5;
+    low.nice = 5;
+    let mut high_ticks = 0;
+    let mut low_ticks = 0;
+    scheduler.enqueue(&mut high);
+    scheduler.enqueue(&mut low);
+    for _ in 0..200 {
+        let id = scheduler.pick_next().unwrap();
+        let entity = if id == high.id { &mut high } else { &mut low };
+        loop {
+            if id == ThreadId(20) {
+                high_ticks = high_ticks + 1;
+            } else {
+                low_ticks = low_ticks + 1;
+            }
+            if scheduler.tick(entity) {
+                break;
+            }
+        }
+        scheduler.enqueue(entity);
+    }
+    // weights 3121 and 335, the nice -5 thread gets roughly nine times the cpu
+    assert!(high_ticks > low_ticks * 6);
+}
@@ -0,0 +1,112 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use super::{Entity, Scheduler};
+use crate::thread::{ThreadId, MAX_THREADS};
+
+pub const LEVELS: usize = 4;
+
+// ticks a thread may run at each level before it is moved down
+const TIME_SLICES: [u64; LEVELS] = [1, 2, 4, 8];
+
+// every thread goes back to its base level this often, so cpu bound ones don't starve
+const BOOST_INTERVAL: u64 = 100;
+
+// multi-level feedback queue: threads that use up their slice drop a level,
+// threads that block keep theirs
+pub struct Mlfq {
+    levels: Vec<VecDeque<ThreadId>>,
+    ticks: u64,
+    epoch: u64,
+}
+
+impl Mlfq {
+
+    pub fn new() -> Mlfq {
+        return Mlfq {
+            levels: (0..LEVELS).map(|_| VecDeque::with_capacity(MAX_THREADS)).collect(),
+            ticks: 0,
+            epoch: 0
+        };
+    }
+
+    // negative nice values start at the top, positive ones lower down
+    fn base_level(nice: i8) -> usize {
+        if nice <= 0 {
+            return 0;
+        }
+        return (nice as usize * LEVELS / 20).min(LEVELS - 1);
+    }
+
+    fn refresh(&self, entity: &mut Entity) {
+        let base = Mlfq::base_level(entity.nice);
+        if entity.boost_epoch != self.epoch || entity.level < base {
+            entity.boost_epoch = self.epoch;
+            entity.level = base;
+            entity.slice = 0;
+        }
+    }
+}
+
+impl Scheduler for Mlfq {
+
+    fn name(&self) -> &'static str {
+        return "mlfq";
+    }
+
+    fn enqueue(&mut self, entity: &mut Entity) {
+        self.refresh(entity);
+        self.levels[entity.level].push_back(entity.id);
+    }
+
+    fn pick_next(&mut self) -> Option<ThreadId> {
+        return self.levels.iter_mut().find_map(|level| level.pop_front());
+    }
+
+    fn tick(&mut self, entity: &mut Entity) -> bool {
+        self.ticks = self.ticks + 1;
+        if self.ticks % BOOST_INTERVAL == 0 {
+            // queued threads are moved up on their next enqueue
+            self.epoch = self.epoch + 1;
+            for level in 1..LEVELS {
+                while let Some(id) = self.levels[level].pop_front() {
+                    self.levels[0].push_back(id);
+                }
+            }
+        }
+        self.refresh(entity);
+        if entity.slice >= TIME_SLICES[entity.level] {
+            entity.level = (entity.level + 1).min(LEVELS - 1);
+            entity.slice = 0;
+            return true;
+        }
+        // a higher level thread is waiting
+        return self.levels[..entity.level].iter().any(|level| !level.is_empty());
+    }
+}
+
+#[test_case]
+fn test_mlfq_demotes_cpu_bound_threads() {
+    let mut scheduler = Mlfq::new();
+    let mut busy = Entity::new(ThreadId(10));
+    let mut interactive = Entity::new(ThreadId(11));
+    scheduler.enqueue(&mut busy);
+    assert_eq!(scheduler.pick_next(), Some(ThreadId(10)));
+    busy.slice = 1;
+    assert!(scheduler.tick(&mut busy));
+    assert_eq!(busy.level, 1);
+    scheduler.enqueue(&mut busy);
+    scheduler.enqueue(&mut interactive);
+    assert_eq!(scheduler.pick_next(), Some(ThreadId(11)));
+    assert_eq!(scheduler.pick_next(), Some(ThreadId(10)));
+}
+
+#[test_case]
+fn test_mlfq_nice_sets_base_level() {
+    let mut scheduler = Mlfq::new();
+    let mut background = Entity::new(ThreadId(12));
+    background.nice = 19;
+    scheduler.enqueue(&mut background);
+    assert_eq!(background.level, LEVELS - 1);
+    assert_eq!(Mlfq::base_level(-5), 0);
+}
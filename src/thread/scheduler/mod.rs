@@ -0,0 +1,102 @@
+// run queue policies, the thread table asks one of these which thread runs next
+
+use alloc::boxed::Box;
+
+use super::ThreadId;
+
+pub mod cfs;
+pub mod mlfq;
+pub mod round_robin;
+
+pub use cfs::Cfs;
+pub use mlfq::Mlfq;
+pub use round_robin::RoundRobin;
+
+pub const MIN_NICE: i8 = -20;
+pub const MAX_NICE: i8 = 19;
+
+// per thread scheduling state, owned by the thread table and lent to the policy
+#[derive(Debug, Clone, Copy)]
+pub struct Entity {
+    pub id: ThreadId,
+    pub nice: i8,
+    // ticks spent running in total, and at the current mlfq level
+    pub runtime: u64,
+    pub slice: u64,
+    // mlfq queue level, 0 is the highest
+    pub level: usize,
+    pub boost_epoch: u64,
+    // cfs virtual runtime, in ticks scaled by NICE_0_WEIGHT
+    pub vruntime: u64,
+}
+
+impl Entity {
+    pub fn new(id: ThreadId) -> Entity {
+        return Entity {
+            id,
+            nice: 0,
+            runtime: 0,
+            slice: 0,
+            level: 0,
+            boost_epoch: 0,
+            vruntime: 0
+        };
+    }
+}
+
+// implementations must not allocate after `new`, they run in the timer interrupt
+pub trait Scheduler: Send {
+    fn name(&self) -> &'static str;
+
+    // the thread became runnable: it was spawned, woken up or preempted
+    fn enqueue(&mut self, entity: &mut Entity);
+
+    fn pick_next(&mut self) -> Option<ThreadId>;
+
+    // charges one tick to the running thread, true when it should give up the cpu
+    fn tick(&mut self, entity: &mut Entity) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    RoundRobin,
+    Mlfq,
+    Cfs,
+}
+
+impl Policy {
+    // picked at build time with the `sched-mlfq` or `sched-cfs` feature
+    #[cfg(feature = "sched-cfs")]
+    pub const DEFAULT: Policy = Policy::Cfs;
+    #[cfg(all(feature = "sched-mlfq", not(feature = "sched-cfs")))]
+    pub const DEFAULT: Policy = Policy::Mlfq;
+    #[cfg(not(any(feature = "sched-mlfq", feature = "sched-cfs")))]
+    pub const DEFAULT: Policy = Policy::RoundRobin;
+
+    pub fn build(self) -> Box<dyn Scheduler> {
+        return match self {
+            Policy::RoundRobin => Box::new(RoundRobin::new()),
+            Policy::Mlfq => Box::new(Mlfq::new()),
+            Policy::Cfs => Box::new(Cfs::new())
+        };
+    }
+}
+
+// linux's table, each nice level is about 10% of cpu time
+const NICE_WEIGHTS: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291,
+    29154, 23254, 18705, 14949, 11916,
+    9548, 7620, 6100, 4904, 3906,
+    3121, 2501, 1991, 1586, 1277,
+    1024, 820, 655, 526, 423,
+    335, 272, 215, 172, 137,
+    110, 87, 70, 56, 45,
+    36, 29, 23, 18, 15,
+];
+
+pub const NICE_0_WEIGHT: u64 = 1024;
+
+pub fn nice_weight(nice: i8) -> u64 {
+    let nice = nice.max(MIN_NICE).min(MAX_NICE);
+    return NICE_WEIGHTS[(nice - MIN_NICE) as usize];
+}
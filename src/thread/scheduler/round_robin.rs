@@ -0,0 +1,52 @@
+use alloc::collections::VecDeque;
+
+use super::{Entity, Scheduler};
+use crate::thread::{ThreadId, MAX_THREADS};
+
+// threads take turns in the order they became ready, one tick each
+pub struct RoundRobin {
+    ready: VecDeque<ThreadId>,
+}
+
+impl RoundRobin {
+
+    pub fn new() -> RoundRobin {
+        return RoundRobin {
+            ready: VecDeque::with_capacity(MAX_THREADS)
+        };
+    }
+}
+
+impl Scheduler for RoundRobin {
+
+    fn name(&self) -> &'static str {
+        return "round robin";
+    }
+
+    fn enqueue(&mut self, entity: &mut Entity) {
+        self.ready.push_back(entity.id);
+    }
+
+    fn pick_next(&mut self) -> Option<ThreadId> {
+        return self.ready.pop_front();
+    }
+
+    fn tick(&mut self, _entity: &mut Entity) -> bool {
+        return true;
+    }
+}
+
+#[test_case]
+fn test_round_robin_order() {
+    let mut scheduler = RoundRobin::new();
+    let mut first = Entity::new(ThreadId(3));
+    let mut second = Entity::new(ThreadId(1));
+    scheduler.enqueue(&mut first);
+    scheduler.enqueue(&mut second);
+    assert_eq!(scheduler.pick_next(), Some(ThreadId(3)));
+    assert!(scheduler.tick(&mut first));
+    scheduler.enqueue(&mut first);
+    assert_eq!(scheduler.pick_next(), Some(ThreadId(1)));
+    assert_eq!(scheduler.pick_next(), Some(ThreadId(3)));
+    assert_eq!(scheduler.pick_next(), None);
+}
@@ -0,0 +1,52 @@
+use x86_64::VirtAddr;
+
+use crate::gdt;
+
+const RFLAGS_INTERRUPTS: u64 = 0x202;
+
+// where `run` places a program and its stack
+pub const PROGRAM_START: u64 = 0x_1000_0000;
+pub const STACK_START: u64 = 0x_1001_0000;
+pub const STACK_SIZE: u64 = 4096;
+
+global_asm!(r#"
+.global jump_to_user_mode
+// rdi = entry, rsi = stack, rdx = code selector, rcx = data selector, r8 = rflags
+jump_to_user_mode:
+    movw %cx, %ds
+    movw %cx, %es
+    pushq %rcx
+    pushq %rsi
+    pushq %r8
+    pushq %rdx
+    pushq %rdi
+    // the kernel gs base waits in the shadow register until the next entry
+    swapgs
+    iretq
+"#);
+
+extern "C" {
+    fn jump_to_user_mode(entry: u64, stack: u64, code_selector: u64, data_selector: u64, rflags: u64) -> !;
+}
+
+// runs `entry` in ring 3 on `stack_top` with interrupts enabled, both must be
+// mapped user accessible, see `memory::map_user_region`
+pub unsafe fn enter(entry: VirtAddr, stack_top: VirtAddr) -> ! {
+    let selectors = gdt::selectors();
+    jump_to_user_mode(
+        entry.as_u64(),
+        stack_top.as_u64(),
+        selectors.user_code_selector.0 as u64,
+        selectors.user_data_selector.0 as u64,
+        RFLAGS_INTERRUPTS
+    );
+}
+
+// copies `program` to PROGRAM_START, gives it a stack and runs it in ring 3. the
+// memory stays mapped, so this is for tests and runs once per boot
+pub unsafe fn run(program: &[u8]) -> ! {
+    crate::memory::map_user_region(VirtAddr::new(PROGRAM_START), program.len() as u64).expect("mapping user code failed");
+    crate::memory::map_user_region(VirtAddr::new(STACK_START), STACK_SIZE).expect("mapping user stack failed");
+    core::ptr::copy_nonoverlapping(program.as_ptr(), PROGRAM_START as *mut u8, program.len());
+    enter(VirtAddr::new(PROGRAM_START), VirtAddr::new(STACK_START + STACK_SIZE));
+}
@@ -0,0 +1,194 @@
+const ESC: char = '\x1b';
+const MAX_PARAMS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Params {
+    values: [u16; MAX_PARAMS],
+    len: usize,
+}
+
+impl Params {
+
+    const fn new() -> Params {
+        return Params {
+            values: [0; MAX_PARAMS],
+            len: 0
+        };
+    }
+
+    // missing or zero parameters take the default value
+    pub fn get(&self, index: usize, default: u16) -> u16 {
+        return match self.values[..self.len].get(index) {
+            Some(0) | None => default,
+            Some(value) => *value
+        };
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        let len = self.len.max(1); // "ESC[m" means "ESC[0m"
+        return self.values[..len].iter().copied();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Print(char),
+    SelectGraphicRendition(Params),
+    CursorUp(u16),
+    CursorDown(u16),
+    CursorForward(u16),
+    CursorBack(u16),
+    CursorPosition(u16, u16),
+    EraseInDisplay(u16),
+    EraseInLine(u16),
+    SaveCursor,
+    RestoreCursor,
+    ShowCursor(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    Csi,
+}
+
+pub struct Parser {
+    state: State,
+    params: Params,
+    private: bool,
+}
+
+impl Parser {
+
+    pub const fn new() -> Parser {
+        return Parser {
+            state: State::Ground,
+            params: Params::new(),
+            private: false
+        };
+    }
+
+    pub fn advance(&mut self, c: char) -> Option<Action> {
+        return match self.state {
+            State::Ground => match c {
+                ESC => {
+                    self.state = State::Escape;
+                    None
+                },
+                c => Some(Action::Print(c))
+            },
+            State::Escape => {
+                self.state = State::Ground;
+                match c {
+                    '[' => {
+                        self.state = State::Csi;
+                        self.params = Params::new();
+                        self.private = false;
+                        None
+                    },
+                    '7' => Some(Action::SaveCursor),
+                    '8' => Some(Action::RestoreCursor),
+                    _ => None
+                }
+            },
+            State::Csi => self.csi(c)
+        };
+    }
+
+    fn csi(&mut self, c: char) -> Option<Action> {
+        match c {
+            '0'..='9' => {
+                if self.params.len == 0 {
+                    self.params.len = 1;
+                }
+                let value = &mut self.params.values[self.params.len - 1];
+                *value = value.saturating_mul(10).saturating_add(c as u16 - '0' as u16);
+                return None;
+            },
+            ';' => {
+                if self.params.len == 0 {
+                    self.params.len = 1;
+                }
+                if self.params.len < MAX_PARAMS {
+                    self.params.len = self.params.len + 1;
+                }
+                return None;
+            },
+            '?' => {
+                self.private = true;
+                return None;
+            },
+            '\x40'..='\x7e' => {
+                self.state = State::Ground;
+                return self.dispatch(c);
+            },
+            _ => {
+                // not a valid control sequence, drop it
+                self.state = State::Ground;
+                return None;
+            }
+        }
+    }
+
+    fn dispatch(&self, command: char) -> Option<Action> {
+        let params = &self.params;
+        if self.private {
+            return match (command, params.get(0, 0)) {
+                ('h', 25) => Some(Action::ShowCursor(true)),
+                ('l', 25) => Some(Action::ShowCursor(false)),
+                _ => None
+            };
+        }
+        return match command {
+            'm' => Some(Action::SelectGraphicRendition(*params)),
+            'A' => Some(Action::CursorUp(params.get(0, 1))),
+            'B' => Some(Action::CursorDown(params.get(0, 1))),
+            'C' => Some(Action::CursorForward(params.get(0, 1))),
+            'D' => Some(Action::CursorBack(params.get(0, 1))),
+            'H' | 'f' => Some(Action::CursorPosition(params.get(0, 1), params.get(1, 1))),
+            'J' => Some(Action::EraseInDisplay(params.get(0, 0))),
+            'K' => Some(Action::EraseInLine(params.get(0, 0))),
+            's' => Some(Action::SaveCursor),
+            'u' => Some(Action::RestoreCursor),
+            _ => None
+        };
+    }
+}
+
+#[test_case]
+fn test_parse_print_and_sgr() {
+    let mut parser = Parser::new();
+    assert_eq!(parser.advance('a'), Some(Action::Print('a')));
+    let mut action = None;
+    for c in "\x1b[1;31m".chars() {
+        action = parser.advance(c);
+    }
+    match action {
+        Some(Action::SelectGraphicRendition(params)) => {
+            let mut values = params.iter();
+            assert_eq!(values.next(), Some(1));
+            assert_eq!(values.next(), Some(31));
+            assert_eq!(values.next(), None);
+        },
+        _ => panic!("expected sgr, got {:?}", action)
+    }
+}
+
+#[test_case]
+fn test_parse_cursor_movement() {
+    let mut parser = Parser::new();
+    let mut parse = |sequence: &str| {
+        let mut action = None;
+        for c in sequence.chars() {
+            action = parser.advance(c);
+        }
+        return action;
+    };
+    assert_eq!(parse("\x1b[A"), Some(Action::CursorUp(1)));
+    assert_eq!(parse("\x1b[5C"), Some(Action::CursorForward(5)));
+    assert_eq!(parse("\x1b[;7H"), Some(Action::CursorPosition(1, 7)));
+    assert_eq!(parse("\x1b[2J"), Some(Action::EraseInDisplay(2)));
+    assert_eq!(parse("\x1b[?25l"), Some(Action::ShowCursor(false)));
+    assert_eq!(parse("\x1b7"), Some(Action::SaveCursor));
+}
@@ -0,0 +1,196 @@
+use super::{Buffer, Writer, BUFFER_HEIGHT, STATUS_ROW};
+use super::scrollback::Scrollback;
+use crate::interrupts::keyboard::{Key, KeyEvent, Modifiers};
+use crate::interrupts::keyboard::bindings::{self, KeyCombo};
+use crate::sync::IrqSpinLock;
+
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+pub const NUM_CONSOLES: usize = 6;
+const INPUT_QUEUE_SIZE: usize = 128;
+
+lazy_static! {
+    pub static ref CONSOLES: IrqSpinLock<Consoles> = IrqSpinLock::named("consoles", Consoles::new());
+}
+
+// only borrowed for a single access, the writers don't keep it
+pub(super) fn vga() -> &'static mut Buffer {
+    return unsafe { &mut *(0xb8000 as *mut Buffer) };
+}
+
+pub struct InputQueue {
+    chars: [char; INPUT_QUEUE_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl InputQueue {
+
+    pub const fn new() -> InputQueue {
+        return InputQueue {
+            chars: ['\0'; INPUT_QUEUE_SIZE],
+            head: 0,
+            len: 0
+        };
+    }
+
+    pub fn push(&mut self, c: char) {
+        if self.len == INPUT_QUEUE_SIZE {
+            return;
+        }
+        self.chars[(self.head + self.len) % INPUT_QUEUE_SIZE] = c;
+        self.len = self.len + 1;
+    }
+
+    pub fn pop(&mut self) -> Option<char> {
+        if self.len == 0 {
+            return None;
+        }
+        let c = self.chars[self.head];
+        self.head = (self.head + 1) % INPUT_QUEUE_SIZE;
+        self.len = self.len - 1;
+        return Some(c);
+    }
+}
+
+pub struct Consoles {
+    writers: [Writer; NUM_CONSOLES],
+    active: usize,
+    output: usize,
+}
+
+impl Consoles {
+
+    fn new() -> Consoles {
+        // console 0 starts on screen, the others draw into their backing buffers
+        return Consoles {
+            writers: [
+                Writer::new(true),
+                Writer::new(false),
+                Writer::new(false),
+                Writer::new(false),
+                Writer::new(false),
+                Writer::new(false),
+            ],
+            active: 0,
+            output: 0
+        };
+    }
+
+    pub fn active_index(&self) -> usize {
+        return self.active;
+    }
+
+    pub fn active(&mut self) -> &mut Writer {
+        return &mut self.writers[self.active];
+    }
+
+    // the console that print! writes to
+    pub fn output(&mut self) -> &mut Writer {
+        return &mut self.writers[self.output];
+    }
+
+    pub fn set_output(&mut self, index: usize) {
+        if index < NUM_CONSOLES {
+            self.output = index;
+        }
+    }
+
+    pub fn get(&mut self, index: usize) -> Option<&mut Writer> {
+        return self.writers.get_mut(index);
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Writer> {
+        return self.writers.iter_mut();
+    }
+
+    pub fn switch(&mut self, index: usize) {
+        if index == self.active || index >= NUM_CONSOLES {
+            return;
+        }
+        let old = self.active;
+        let mouse_cursor = self.writers[old].mouse_cursor_position();
+        self.writers[old].hide_mouse_cursor();
+        // the status bar is shared by all consoles and stays on screen
+        let status = vga().chars[STATUS_ROW];
+        self.writers[old].deactivate();
+        self.writers[index].activate();
+        vga().chars[STATUS_ROW] = status;
+        if let Some((row, col)) = mouse_cursor {
+            self.writers[index].set_mouse_cursor(row, col);
+        }
+        self.active = index;
+    }
+}
+
+fn switch_console(event: KeyEvent) {
+    if let Key::F(n) = event.key {
+        CONSOLES.lock().switch(n as usize - 1);
+    }
+}
+
+pub fn init() {
+    for n in 1..=NUM_CONSOLES as u8 {
+        let combo = KeyCombo::new(Modifiers::ALT, Key::F(n));
+        bindings::register(combo, switch_console).expect("alt+fn already bound");
+    }
+}
+
+pub fn switch(index: usize) {
+    CONSOLES.lock().switch(index);
+}
+
+pub fn read_char(index: usize) -> Option<char> {
+    return CONSOLES.lock().get(index).and_then(|console| console.read_char());
+}
+
+// the history is allocated here, printing never touches the heap under the console lock
+pub fn init_scrollback(lines: usize) {
+    let mut scrollbacks: Vec<Option<Scrollback>> = (0..NUM_CONSOLES).map(|_| Some(Scrollback::new(lines))).collect();
+    {
+        let mut consoles = CONSOLES.lock();
+        for (console, scrollback) in consoles.iter_mut().zip(scrollbacks.iter_mut()) {
+            *scrollback = console.replace_scrollback(scrollback.take());
+        }
+    }
+    // the replaced ones, if any, are freed without the lock
+    drop(scrollbacks);
+    let page_up = KeyCombo::new(Modifiers::SHIFT, Key::PageUp);
+    let page_down = KeyCombo::new(Modifiers::SHIFT, Key::PageDown);
+    bindings::register(page_up, |_| CONSOLES.lock().active().scroll_up(BUFFER_HEIGHT / 2))
+        .expect("shift+pgup already bound");
+    bindings::register(page_down, |_| CONSOLES.lock().active().scroll_down(BUFFER_HEIGHT / 2))
+        .expect("shift+pgdn already bound");
+}
+
+#[test_case]
+fn test_switch_preserves_contents() {
+    use core::fmt::Write;
+
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let second = consoles.get(1).unwrap();
+        writeln!(second, "second console").unwrap();
+        let row = second.row_position - 1;
+        writeln!(consoles.get(0).unwrap(), "\nfirst console").unwrap();
+        let first = vga().chars[BUFFER_HEIGHT - 2][0];
+
+        consoles.switch(1);
+        assert_eq!(consoles.active_index(), 1);
+        assert_eq!(vga().chars[row][0].ascii_character, b's');
+
+        consoles.switch(0);
+        assert_eq!(vga().chars[BUFFER_HEIGHT - 2][0], first);
+    });
+}
+
+#[test_case]
+fn test_input_queue() {
+    let mut queue = InputQueue::new();
+    queue.push('a');
+    queue.push('é');
+    assert_eq!(queue.pop(), Some('a'));
+    assert_eq!(queue.pop(), Some('é'));
+    assert_eq!(queue.pop(), None);
+}
@@ -0,0 +1,75 @@
+use super::BUFFER_WIDTH;
+
+use x86_64::instructions::port::Port;
+
+const CRTC_ADDRESS: u16 = 0x3d4;
+const CRTC_DATA: u16 = 0x3d5;
+
+const CURSOR_START: u8 = 0x0a;
+const CURSOR_END: u8 = 0x0b;
+const CURSOR_LOCATION_HIGH: u8 = 0x0e;
+const CURSOR_LOCATION_LOW: u8 = 0x0f;
+
+const CURSOR_DISABLE: u8 = 0x20;
+
+// scanlines of the 16 pixel high character cell covered by the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorShape {
+    Underline,
+    HalfBlock,
+    Block,
+}
+
+impl CursorShape {
+    fn scanlines(&self) -> (u8, u8) {
+        return match self {
+            CursorShape::Underline => (14, 15),
+            CursorShape::HalfBlock => (8, 15),
+            CursorShape::Block => (0, 15)
+        };
+    }
+}
+
+fn read_register(index: u8) -> u8 {
+    let mut address: Port<u8> = Port::new(CRTC_ADDRESS);
+    let mut data: Port<u8> = Port::new(CRTC_DATA);
+    unsafe {
+        address.write(index);
+        return data.read();
+    }
+}
+
+fn write_register(index: u8, value: u8) {
+    let mut address: Port<u8> = Port::new(CRTC_ADDRESS);
+    let mut data: Port<u8> = Port::new(CRTC_DATA);
+    unsafe {
+        address.write(index);
+        data.write(value);
+    }
+}
+
+pub fn enable(shape: CursorShape) {
+    let (start, end) = shape.scanlines();
+    write_register(CURSOR_START, (read_register(CURSOR_START) & 0xc0) | start);
+    write_register(CURSOR_END, (read_register(CURSOR_END) & 0xe0) | end);
+}
+
+pub fn disable() {
+    write_register(CURSOR_START, read_register(CURSOR_START) | CURSOR_DISABLE);
+}
+
+pub fn show() {
+    write_register(CURSOR_START, read_register(CURSOR_START) & !CURSOR_DISABLE);
+}
+
+pub fn set_position(row: usize, col: usize) {
+    let position = (row * BUFFER_WIDTH + col) as u16;
+    write_register(CURSOR_LOCATION_LOW, position as u8);
+    write_register(CURSOR_LOCATION_HIGH, (position >> 8) as u8);
+}
+
+pub fn position() -> (usize, usize) {
+    let position = (read_register(CURSOR_LOCATION_HIGH) as usize) << 8
+        | read_register(CURSOR_LOCATION_LOW) as usize;
+    return (position / BUFFER_WIDTH, position % BUFFER_WIDTH);
+}
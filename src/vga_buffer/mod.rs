@@ -0,0 +1,793 @@
+use core::fmt::{Arguments,Result,Write};
+
+pub mod ansi;
+pub mod console;
+pub mod cp437;
+pub mod cursor;
+pub mod scrollback;
+pub mod status;
+pub mod utf8;
+use scrollback::Scrollback;
+use utf8::Utf8Decoder;
+use console::InputQueue;
+
+pub use console::{CONSOLES, NUM_CONSOLES};
+
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => ($crate::console::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! console_print {
+    ($console:expr, $($arg:tt)*) => ($crate::vga_buffer::_console_print($console, format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! console_println {
+    ($console:expr) => ($crate::console_print!($console, "\n"));
+    ($console:expr, $($arg:tt)*) => ($crate::console_print!($console, "{}\n", format_args!($($arg)*)));
+}
+
+pub fn _print(args: Arguments) {
+    // once a graphics mode is set the text buffer is no longer displayed
+    if crate::framebuffer::_print(args) {
+        return;
+    }
+    CONSOLES.lock().output().write_fmt(args).unwrap();
+}
+
+pub fn _console_print(console: usize, args: Arguments) {
+    if let Some(writer) = CONSOLES.lock().get(console) {
+        writer.write_fmt(args).unwrap();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Color {
+    Black = 0,
+    Blue = 1,
+    Green = 2,
+    Cyan = 3,
+    Red = 4,
+    Magenta = 5,
+    Brown = 6,
+    LightGray = 7,
+    DarkGray = 8,
+    LightBlue = 9,
+    LightGreen = 10,
+    LightCyan = 11,
+    LightRed = 12,
+    Pink = 13,
+    Yellow = 14,
+    White = 15,
+}
+
+// sgr colour numbers are in rgb bit order, the vga palette in bgr
+const ANSI_COLORS: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Brown,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::LightGray,
+];
+
+const BRIGHT: u8 = 0x08;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct ColorCode(u8);
+
+const DEFAULT_COLOR: ColorCode = ColorCode((Color::Black as u8) << 4 | Color::Green as u8);
+
+impl ColorCode {
+    pub fn new(background: Color, foreground: Color) -> ColorCode {
+        return ColorCode((background as u8) << 4 | foreground as u8);
+    }
+
+    fn inverted(&self) -> ColorCode {
+        return ColorCode(self.0 << 4 | self.0 >> 4);
+    }
+
+    fn foreground(&self) -> u8 {
+        return self.0 & 0x0f;
+    }
+
+    fn background(&self) -> u8 {
+        return self.0 >> 4;
+    }
+
+    fn with_foreground(&self, color: u8) -> ColorCode {
+        return ColorCode(self.0 & 0xf0 | color & 0x0f);
+    }
+
+    fn with_background(&self, color: u8) -> ColorCode {
+        return ColorCode((color & 0x0f) << 4 | self.0 & 0x0f);
+    }
+
+    // applies one sgr parameter, bold turns the foreground colours bright
+    pub fn select_graphic_rendition(&self, param: u16, bold: &mut bool) -> ColorCode {
+        let color = *self;
+        let bright = match *bold {
+            true => BRIGHT,
+            false => 0
+        };
+        return match param {
+            0 => {
+                *bold = false;
+                DEFAULT_COLOR
+            },
+            1 => {
+                *bold = true;
+                color.with_foreground(color.foreground() | BRIGHT)
+            },
+            22 => {
+                *bold = false;
+                color.with_foreground(color.foreground() & !BRIGHT)
+            },
+            30..=37 => color.with_foreground(ANSI_COLORS[(param - 30) as usize] as u8 | bright),
+            39 => color.with_foreground(DEFAULT_COLOR.foreground() | bright),
+            40..=47 => color.with_background(ANSI_COLORS[(param - 40) as usize] as u8),
+            49 => color.with_background(DEFAULT_COLOR.background()),
+            90..=97 => color.with_foreground(ANSI_COLORS[(param - 90) as usize] as u8 | BRIGHT),
+            100..=107 => color.with_background(ANSI_COLORS[(param - 100) as usize] as u8 | BRIGHT),
+            _ => color
+        };
+    }
+
+    // the sgr sequence that selects this colour on an ansi terminal
+    pub fn write_sgr(&self, out: &mut impl Write) -> Result {
+        if *self == DEFAULT_COLOR {
+            return out.write_str("\x1b[0m");
+        }
+        let ansi = |color: u8| ANSI_COLORS.iter().position(|ansi| *ansi as u8 == color & !BRIGHT).unwrap() as u8;
+        let (foreground, background) = (self.foreground(), self.background());
+        let foreground = ansi(foreground) + if foreground & BRIGHT != 0 { 90 } else { 30 };
+        let background = ansi(background) + if background & BRIGHT != 0 { 100 } else { 40 };
+        return write!(out, "\x1b[0;{};{}m", foreground, background);
+    }
+}
+
+impl Default for ColorCode {
+    fn default() -> ColorCode {
+        return DEFAULT_COLOR;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+struct ScreenChar {
+    ascii_character: u8,
+    color_code: ColorCode,
+}
+
+pub const BUFFER_HEIGHT: usize = 25;
+pub const BUFFER_WIDTH: usize = 80;
+
+// the top row belongs to the status bar, console text starts below it
+pub const STATUS_ROW: usize = 0;
+pub const FIRST_TEXT_ROW: usize = STATUS_ROW + 1;
+
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+struct Buffer {
+    chars: [[ScreenChar; BUFFER_WIDTH]; BUFFER_HEIGHT]
+}
+
+// the vga memory while the console is on screen, its own copy otherwise
+fn screen(active: bool, backing: &mut Buffer) -> &mut Buffer {
+    if active {
+        return console::vga();
+    }
+    return backing;
+}
+
+impl Buffer {
+    const fn blank() -> Buffer {
+        let blank = ScreenChar {
+            ascii_character: 0,
+            color_code: ColorCode(0)
+        };
+        return Buffer {
+            chars: [[blank; BUFFER_WIDTH]; BUFFER_HEIGHT]
+        };
+    }
+}
+
+pub struct Writer {
+    column_position: usize,
+    row_position: usize,
+    color_code: ColorCode,
+    // drawn into while the console is not on screen
+    backing: Buffer,
+    mouse_cursor: Option<MouseCursor>,
+    decoder: Utf8Decoder,
+    scrollback: Option<Scrollback>,
+    ansi: ansi::Parser,
+    bold: bool,
+    saved_cursor: (usize, usize, ColorCode),
+    // whether the vga memory or `backing` holds what the console shows
+    active: bool,
+    input: InputQueue,
+}
+
+#[derive(Clone, Copy)]
+struct MouseCursor {
+    row: usize,
+    col: usize,
+    saved: ScreenChar,
+}
+
+impl Writer {
+
+    fn new(active: bool) -> Writer {
+        return Writer {
+            column_position: 0,
+            row_position: FIRST_TEXT_ROW,
+            color_code: DEFAULT_COLOR,
+            backing: Buffer::blank(),
+            mouse_cursor: None,
+            decoder: Utf8Decoder::new(),
+            scrollback: None,
+            ansi: ansi::Parser::new(),
+            bold: false,
+            saved_cursor: (FIRST_TEXT_ROW, 0, DEFAULT_COLOR),
+            active,
+            input: InputQueue::new()
+        };
+    }
+
+    // puts the console's contents on screen, the caller keeps the status row
+    fn activate(&mut self) {
+        *console::vga() = self.backing;
+        self.active = true;
+        let scrolled = self.scrollback.as_ref().map_or(false, |scrollback| scrollback.offset() > 0);
+        if scrolled {
+            cursor::disable();
+        }
+        else {
+            cursor::show();
+        }
+        self.update_cursor();
+    }
+
+    fn deactivate(&mut self) {
+        self.backing = *console::vga();
+        self.active = false;
+    }
+
+    fn buffer(&mut self) -> &mut Buffer {
+        return screen(self.active, &mut self.backing);
+    }
+
+    // returns the one it replaces, to be dropped once the console lock is released
+    pub fn replace_scrollback(&mut self, scrollback: Option<Scrollback>) -> Option<Scrollback> {
+        return core::mem::replace(&mut self.scrollback, scrollback);
+    }
+
+    pub fn push_input(&mut self, c: char) {
+        self.input.push(c);
+    }
+
+    pub fn read_char(&mut self) -> Option<char> {
+        return self.input.pop();
+    }
+
+    fn new_line(&mut self) {
+        self.snap_back();
+        let (row, col) = (self.row_position, self.column_position);
+        self.buffer().chars[row][col] = ScreenChar {
+            ascii_character: b'\n',
+            color_code: ColorCode::new(Color::Black, Color::Black)
+        };
+        self.row_position = self.row_position + 1;
+        self.column_position = 0;
+        if self.row_position == BUFFER_HEIGHT {
+            self.row_position = BUFFER_HEIGHT - 1;
+            let mouse_cursor = self.mouse_cursor;
+            self.hide_mouse_cursor();
+            let top = self.buffer().chars[FIRST_TEXT_ROW];
+            if let Some(scrollback) = &mut self.scrollback {
+                scrollback.push(top);
+            }
+            let buffer = self.buffer();
+            for row in FIRST_TEXT_ROW..BUFFER_HEIGHT - 1 {
+                buffer.chars[row] = buffer.chars[row + 1];
+            }
+            self.clear_row(BUFFER_HEIGHT - 1);
+            if let Some(cursor) = mouse_cursor {
+                self.set_mouse_cursor(cursor.row, cursor.col);
+            }
+        }
+        self.update_cursor();
+    }
+
+    fn update_cursor(&self) {
+        if self.active {
+            cursor::set_position(self.row_position, self.column_position);
+        }
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll(lines as isize);
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll(-(lines as isize));
+    }
+
+    // any new output brings the view back to the live screen
+    pub fn snap_back(&mut self) {
+        let offset = self.scrollback.as_ref().map_or(0, |scrollback| scrollback.offset());
+        if offset > 0 {
+            self.scroll(-(offset as isize));
+        }
+    }
+
+    fn scroll(&mut self, lines: isize) {
+        let scrollback = match &mut self.scrollback {
+            Some(scrollback) => scrollback,
+            None => return
+        };
+        let was_live = scrollback.offset() == 0;
+        let mouse_cursor = self.mouse_cursor;
+        if was_live {
+            self.hide_mouse_cursor();
+        }
+
+        let scrollback = self.scrollback.as_mut().unwrap();
+        let buffer = screen(self.active, &mut self.backing);
+        let restored = scrollback.scroll(lines, &buffer.chars);
+        if scrollback.offset() > 0 {
+            scrollback.render(&mut buffer.chars);
+            if self.active {
+                cursor::disable();
+            }
+            // keep the mouse cursor position so it can be restored on the live screen
+            if was_live {
+                self.mouse_cursor = mouse_cursor;
+            }
+        }
+        else if restored {
+            buffer.chars[FIRST_TEXT_ROW..].copy_from_slice(&scrollback.live()[FIRST_TEXT_ROW..]);
+            if self.active {
+                cursor::show();
+            }
+            self.update_cursor();
+            if let Some(cursor) = self.mouse_cursor.take() {
+                self.set_mouse_cursor(cursor.row, cursor.col);
+            }
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        self.snap_back();
+        if self.row_position > FIRST_TEXT_ROW {
+            self.row_position = self.row_position - 1;
+            self.update_cursor();
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        self.snap_back();
+        if self.row_position < BUFFER_HEIGHT - 1 {
+            self.row_position = self.row_position + 1;
+            self.update_cursor();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        self.snap_back();
+        if self.column_position < BUFFER_WIDTH - 1 {
+            self.column_position = self.column_position + 1;
+        }
+        else if self.row_position < BUFFER_HEIGHT - 1 {
+            self.row_position = self.row_position + 1;
+            self.column_position = 0;
+        }
+        self.update_cursor();
+    }
+
+    pub fn move_left(&mut self) {
+        self.snap_back();
+        if self.column_position > 0 {
+            self.column_position = self.column_position - 1;
+        }
+        else if self.row_position > FIRST_TEXT_ROW {
+            self.row_position = self.row_position - 1;
+            self.column_position = BUFFER_WIDTH - 1;
+        }
+        self.update_cursor();
+    }
+
+    fn clear_row(&mut self, row: usize) {
+        for col in 0..BUFFER_WIDTH {
+            self.buffer().chars[row][col] = ScreenChar {
+                ascii_character: 0,
+                color_code: ColorCode::new(Color::Black, Color::Black)
+            };
+        }
+    }
+
+    pub fn set_mouse_cursor(&mut self, row: usize, col: usize) {
+        self.hide_mouse_cursor();
+        let saved = self.buffer().chars[row][col];
+        self.buffer().chars[row][col] = ScreenChar {
+            ascii_character: saved.ascii_character,
+            color_code: saved.color_code.inverted()
+        };
+        self.mouse_cursor = Some(MouseCursor { row, col, saved });
+    }
+
+    pub fn mouse_cursor_position(&self) -> Option<(usize, usize)> {
+        return self.mouse_cursor.map(|cursor| (cursor.row, cursor.col));
+    }
+
+    pub fn hide_mouse_cursor(&mut self) {
+        if let Some(cursor) = self.mouse_cursor.take() {
+            let current = self.buffer().chars[cursor.row][cursor.col];
+            // only restore the cell if nothing has been written over the cursor
+            if current.ascii_character == cursor.saved.ascii_character
+                && current.color_code == cursor.saved.color_code.inverted() {
+                self.buffer().chars[cursor.row][cursor.col] = cursor.saved;
+            }
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.snap_back();
+        if self.row_position == FIRST_TEXT_ROW && self.column_position == 0 {
+            return;
+        }
+        if self.column_position == 0 {
+            self.row_position = self.row_position - 1;
+            let row = self.row_position;
+            for col in (0..BUFFER_WIDTH).rev() {
+                if self.buffer().chars[row][col].ascii_character == b'\n' {
+                    self.column_position = col + 1;
+                    break;
+                }
+            }
+            self.backspace();
+        }
+        else {
+            let (row, col) = (self.row_position, self.column_position - 1);
+            self.buffer().chars[row][col] = ScreenChar {
+                ascii_character: 0,
+                color_code: ColorCode::new(Color::Black, Color::Black)
+            };
+            self.column_position = self.column_position - 1;
+        }
+        self.update_cursor();
+    }
+
+    pub fn write_string(&mut self, string: &str) {
+        for c in string.chars() {
+            self.write_char(c);
+        }
+    }
+
+    // for utf-8 streams that may split a character across calls
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        let mut decoder = core::mem::replace(&mut self.decoder, Utf8Decoder::new());
+        for byte in bytes {
+            decoder.push(*byte, |c| self.write_char(c));
+        }
+        self.decoder = decoder;
+    }
+
+    pub fn write_char(&mut self, c: char) {
+        match self.ansi.advance(c) {
+            Some(ansi::Action::Print(c)) => self.put_char(c),
+            Some(action) => self.apply(action),
+            None => {}
+        }
+    }
+
+    fn put_char(&mut self, c: char) {
+        match c {
+            '\n' => self.new_line(),
+            c => self.write(cp437::encode(c).unwrap_or(cp437::UNKNOWN))
+        }
+    }
+
+    pub fn write(&mut self, byte: u8) {
+        self.snap_back();
+        match byte {
+            b'\n' => self.new_line(),
+            byte => {
+                if self.column_position == BUFFER_WIDTH - 1 {
+                    self.new_line();
+                }
+
+                let row = self.row_position;
+                let col = self.column_position;
+
+                let color_code = self.color_code;
+                self.buffer().chars[row][col] = ScreenChar {
+                    ascii_character: byte,
+                    color_code
+                };
+                self.column_position = self.column_position + 1;
+                self.update_cursor();
+            }
+        }
+    }
+}
+
+impl Writer {
+
+    fn apply(&mut self, action: ansi::Action) {
+        use ansi::Action;
+
+        self.snap_back();
+        match action {
+            Action::Print(c) => self.put_char(c),
+            Action::SelectGraphicRendition(params) => {
+                for param in params.iter() {
+                    self.select_graphic_rendition(param);
+                }
+            },
+            Action::CursorUp(n) => {
+                self.row_position = self.row_position.saturating_sub(n as usize).max(FIRST_TEXT_ROW);
+            },
+            Action::CursorDown(n) => {
+                self.row_position = (self.row_position + n as usize).min(BUFFER_HEIGHT - 1);
+            },
+            Action::CursorForward(n) => {
+                self.column_position = (self.column_position + n as usize).min(BUFFER_WIDTH - 1);
+            },
+            Action::CursorBack(n) => {
+                self.column_position = self.column_position.saturating_sub(n as usize);
+            },
+            Action::CursorPosition(row, col) => {
+                self.row_position = (FIRST_TEXT_ROW + row as usize - 1).min(BUFFER_HEIGHT - 1);
+                self.column_position = (col as usize - 1).min(BUFFER_WIDTH - 1);
+            },
+            Action::EraseInLine(mode) => {
+                let (row, col) = (self.row_position, self.column_position);
+                match mode {
+                    0 => self.erase(row, col, BUFFER_WIDTH),
+                    1 => self.erase(row, 0, col + 1),
+                    _ => self.erase(row, 0, BUFFER_WIDTH)
+                }
+            },
+            Action::EraseInDisplay(mode) => {
+                let (row, col) = (self.row_position, self.column_position);
+                let (rows, line) = match mode {
+                    0 => (row + 1..BUFFER_HEIGHT, (col, BUFFER_WIDTH)),
+                    1 => (FIRST_TEXT_ROW..row, (0, col + 1)),
+                    _ => (FIRST_TEXT_ROW..BUFFER_HEIGHT, (0, BUFFER_WIDTH))
+                };
+                for other in rows {
+                    self.erase(other, 0, BUFFER_WIDTH);
+                }
+                self.erase(row, line.0, line.1);
+            },
+            Action::SaveCursor => {
+                self.saved_cursor = (self.row_position, self.column_position, self.color_code);
+            },
+            Action::RestoreCursor => {
+                let (row, col, color_code) = self.saved_cursor;
+                self.row_position = row;
+                self.column_position = col;
+                self.color_code = color_code;
+            },
+            Action::ShowCursor(show) => {
+                if self.active && show {
+                    cursor::show();
+                }
+                else if self.active {
+                    cursor::disable();
+                }
+            }
+        }
+        self.update_cursor();
+    }
+
+    fn select_graphic_rendition(&mut self, param: u16) {
+        self.color_code = self.color_code.select_graphic_rendition(param, &mut self.bold);
+    }
+
+    fn erase(&mut self, row: usize, from: usize, to: usize) {
+        let color_code = self.color_code;
+        for col in from..to {
+            self.buffer().chars[row][col] = ScreenChar {
+                ascii_character: 0,
+                color_code
+            };
+        }
+    }
+}
+
+impl Write for Writer {
+    fn write_str(&mut self, s: &str) -> Result {
+        self.write_string(s);
+        return Ok(());
+    }
+
+    fn write_char(&mut self, c: char) -> Result {
+        Writer::write_char(self, c);
+        return Ok(());
+    }
+}
+
+#[test_case]
+fn print_simple() {
+    print!("simple print: {}", 123);
+}
+
+#[test_case]
+fn print_multiple() {
+    print!("\n");
+    for i in 0..=3 {
+        print!("multiple print: {}, ", i);
+    }
+}
+
+#[test_case]
+fn println_simple() {
+    println!("simple println: {}", 123);
+}
+
+#[test_case]
+fn println_multiple() {
+    for i in 0..200 {
+        println!("multiple println: {}, ", i);
+    }
+}
+
+#[test_case]
+fn test_println_output() {
+    use x86_64::instructions::interrupts;
+
+    let s = "Some test string that fits on a single line";
+    interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let writer = consoles.output();
+        writeln!(writer, "\n{}", s);
+        for (i, c) in s.chars().enumerate() {
+            let screen_char = writer.buffer().chars[BUFFER_HEIGHT - 2][i];
+            assert_eq!(char::from(screen_char.ascii_character), c);
+        }
+    });
+}
+
+#[test_case]
+fn test_println_non_ascii() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let writer = consoles.output();
+        writeln!(writer, "\nçé░€").unwrap();
+        let row = writer.buffer().chars[BUFFER_HEIGHT - 2];
+        let written: [u8; 4] = [
+            row[0].ascii_character,
+            row[1].ascii_character,
+            row[2].ascii_character,
+            row[3].ascii_character
+        ];
+        assert_eq!(written, [0x87, 0x82, 0xb0, cp437::UNKNOWN]);
+    });
+}
+
+#[test_case]
+fn test_write_split_utf8() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let writer = consoles.output();
+        let bytes = "\nñ".as_bytes();
+        writer.write_bytes(&bytes[..2]);
+        writer.write_bytes(&bytes[2..]);
+        writer.write_bytes(b"\n");
+        assert_eq!(writer.buffer().chars[BUFFER_HEIGHT - 2][0].ascii_character, 0xa4);
+    });
+}
+
+#[test_case]
+fn test_cursor_follows_writer() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let writer = consoles.output();
+        writer.write_string("\nabc");
+        assert_eq!(cursor::position(), (BUFFER_HEIGHT - 1, 3));
+        writer.move_left();
+        assert_eq!(cursor::position(), (BUFFER_HEIGHT - 1, 2));
+        writer.backspace();
+        assert_eq!(cursor::position(), (BUFFER_HEIGHT - 1, 1));
+    });
+}
+
+#[test_case]
+fn test_scrollback_history() {
+    use x86_64::instructions::interrupts;
+
+    let mut scrollback = Some(Scrollback::new(scrollback::DEFAULT_SCROLLBACK_LINES));
+    interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let writer = consoles.output();
+        if writer.scrollback.is_none() {
+            writer.scrollback = scrollback.take();
+        }
+        for _ in 0..BUFFER_HEIGHT {
+            writer.new_line();
+        }
+        for i in 0..BUFFER_HEIGHT {
+            writeln!(writer, "scrollback line {}", i).unwrap();
+        }
+        let live = writer.buffer().chars;
+
+        writer.scroll_up(1);
+        assert_eq!(writer.buffer().chars[FIRST_TEXT_ROW][16].ascii_character, b'1');
+        assert_eq!(writer.buffer().chars[FIRST_TEXT_ROW + 1][16].ascii_character, b'2');
+
+        writer.write_string("x");
+        for row in 0..BUFFER_HEIGHT - 1 {
+            assert!(writer.buffer().chars[row][..] == live[row][..]);
+        }
+    });
+}
+
+#[test_case]
+fn test_ansi_colors() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let writer = consoles.output();
+        writeln!(writer, "\n\x1b[31;44mr\x1b[1mb\x1b[0md").unwrap();
+        let row = writer.buffer().chars[BUFFER_HEIGHT - 2];
+        assert_eq!(row[0].color_code, ColorCode::new(Color::Blue, Color::Red));
+        assert_eq!(row[1].color_code, ColorCode::new(Color::Blue, Color::LightRed));
+        assert_eq!(row[2].color_code, DEFAULT_COLOR);
+    });
+}
+
+#[test_case]
+fn test_ansi_cursor_movement() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let writer = consoles.output();
+        writer.write_string("\x1b[s\x1b[3;5Hx\x1b[2Dy\x1b[K");
+        let row = FIRST_TEXT_ROW + 2;
+        assert_eq!(writer.buffer().chars[row][4].ascii_character, b'x');
+        assert_eq!(writer.buffer().chars[row][3].ascii_character, b'y');
+        assert_eq!(writer.buffer().chars[row][4].ascii_character, 0);
+        writer.write_string("\x1b[u");
+        assert_eq!(cursor::position(), (writer.row_position, writer.column_position));
+    });
+}
+
+#[test_case]
+fn test_status_row_is_not_scrolled() {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        let mut consoles = CONSOLES.lock();
+        let writer = consoles.output();
+        let status = writer.buffer().chars[STATUS_ROW];
+        for i in 0..BUFFER_HEIGHT * 2 {
+            writeln!(writer, "status line {}", i).unwrap();
+        }
+        writer.write_string("\x1b[2J\x1b[1;1H");
+        assert!(writer.buffer().chars[STATUS_ROW][..] == status[..]);
+        assert_eq!(writer.row_position, FIRST_TEXT_ROW);
+    });
+}
@@ -0,0 +1,94 @@
+use super::{Buffer, ScreenChar, BUFFER_HEIGHT, BUFFER_WIDTH, FIRST_TEXT_ROW};
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+// allocated up front for every console, 160 bytes a line
+pub const DEFAULT_SCROLLBACK_LINES: usize = 500;
+
+pub type Line = [ScreenChar; BUFFER_WIDTH];
+pub type Screen = [Line; BUFFER_HEIGHT];
+
+pub struct Scrollback {
+    // a ring of `capacity` lines, it never grows since lines are pushed with the console locked
+    lines: Vec<Line>,
+    capacity: usize,
+    // the oldest line and how many are kept
+    head: usize,
+    len: usize,
+    offset: usize,
+    // the live screen, saved while the history is being shown. kept inline, scrolling
+    // happens from the keyboard interrupt where the heap may be locked
+    live: Screen,
+}
+
+impl Scrollback {
+
+    pub fn new(capacity: usize) -> Scrollback {
+        return Scrollback {
+            lines: vec![Buffer::blank().chars[0]; capacity],
+            capacity,
+            head: 0,
+            len: 0,
+            offset: 0,
+            live: Buffer::blank().chars
+        };
+    }
+
+    pub fn push(&mut self, line: Line) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.len == self.capacity {
+            self.lines[self.head] = line;
+            self.head = (self.head + 1) % self.capacity;
+            return;
+        }
+        self.lines[(self.head + self.len) % self.capacity] = line;
+        self.len = self.len + 1;
+    }
+
+    // counted from the oldest line
+    fn line(&self, index: usize) -> Option<&Line> {
+        if index >= self.len {
+            return None;
+        }
+        return Some(&self.lines[(self.head + index) % self.capacity]);
+    }
+
+    pub fn offset(&self) -> usize {
+        return self.offset;
+    }
+
+    // moves the view by `lines`, true when that brought it back to the live screen
+    pub fn scroll(&mut self, lines: isize, screen: &Screen) -> bool {
+        let offset = (self.offset as isize + lines).max(0).min(self.len as isize) as usize;
+        if self.offset == 0 && offset > 0 {
+            self.live = *screen;
+        }
+        let restored = self.offset > 0 && offset == 0;
+        self.offset = offset;
+        return restored;
+    }
+
+    // the screen saved when scrolling away from it
+    pub fn live(&self) -> &Screen {
+        return &self.live;
+    }
+
+    pub fn render(&self, screen: &mut Screen) {
+        if self.offset == 0 {
+            return;
+        }
+        let live = &self.live;
+        let start = self.len - self.offset;
+        // the status row is left alone
+        for row in FIRST_TEXT_ROW..BUFFER_HEIGHT {
+            let index = start + row - FIRST_TEXT_ROW;
+            screen[row] = match self.line(index) {
+                Some(line) => *line,
+                None => live[FIRST_TEXT_ROW + index - self.len]
+            };
+        }
+    }
+}
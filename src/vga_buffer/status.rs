@@ -0,0 +1,109 @@
+use super::{Color, ColorCode, ScreenChar, BUFFER_WIDTH, CONSOLES, STATUS_ROW};
+use super::console::vga;
+use super::scrollback::Line;
+use crate::interrupts::{self, keyboard};
+use crate::memory;
+
+use core::fmt::{self, Write};
+use spin::Mutex;
+
+const STATUS_COLOR: ColorCode = ColorCode((Color::Blue as u8) << 4 | Color::White as u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Status {
+    pub uptime_secs: u64,
+    pub heap_free: usize,
+    pub free_frames: usize,
+    pub capslock: bool,
+    pub numlock: bool,
+    pub console: usize,
+}
+
+// what is currently drawn, so unchanged ticks cost no redraw
+static SHOWN: Mutex<Option<Status>> = Mutex::new(None);
+
+struct LineWriter {
+    line: Line,
+    col: usize,
+}
+
+impl Write for LineWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if self.col == BUFFER_WIDTH {
+                break;
+            }
+            self.line[self.col].ascii_character = byte;
+            self.col = self.col + 1;
+        }
+        return Ok(());
+    }
+}
+
+pub fn render(status: &Status) -> Line {
+    let blank = ScreenChar {
+        ascii_character: b' ',
+        color_code: STATUS_COLOR
+    };
+    let mut writer = LineWriter { line: [blank; BUFFER_WIDTH], col: 0 };
+    let secs = status.uptime_secs;
+    write!(
+        writer,
+        " up {:02}:{:02}:{:02} | heap {} KiB free | {} frames free | {} {} | tty{}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        status.heap_free / 1024,
+        status.free_frames,
+        if status.capslock { "CAPS" } else { "caps" },
+        if status.numlock { "NUM" } else { "num" },
+        status.console + 1
+    ).unwrap();
+    return writer.line;
+}
+
+// called from the timer, so every lock is only tried and old values are kept on contention
+pub fn update() {
+    let mut shown = match SHOWN.try_lock() {
+        Some(shown) => shown,
+        None => return
+    };
+    let previous = shown.unwrap_or_default();
+    let (capslock, numlock) = keyboard::try_lock_keys().unwrap_or((previous.capslock, previous.numlock));
+    let free_frames = memory::FRAME_ALLOCATOR.try_lock()
+        .and_then(|frame_allocator| frame_allocator.as_ref().map(|frame_allocator| frame_allocator.free_frames()));
+    let status = Status {
+        uptime_secs: interrupts::uptime_ms() / 1000,
+        heap_free: memory::allocator::try_free().unwrap_or(previous.heap_free),
+        free_frames: free_frames.unwrap_or(previous.free_frames),
+        capslock,
+        numlock,
+        console: CONSOLES.try_lock().map_or(previous.console, |consoles| consoles.active_index())
+    };
+    if *shown == Some(status) {
+        return;
+    }
+    vga().chars[STATUS_ROW] = render(&status);
+    *shown = Some(status);
+}
+
+#[test_case]
+fn test_render_status() {
+    let status = Status {
+        uptime_secs: 3723,
+        heap_free: 2048,
+        free_frames: 10,
+        capslock: true,
+        numlock: false,
+        console: 2
+    };
+    let line = render(&status);
+    let mut text = [0u8; BUFFER_WIDTH];
+    for (i, cell) in line.iter().enumerate() {
+        text[i] = cell.ascii_character;
+        assert_eq!(cell.color_code, STATUS_COLOR);
+    }
+    let expected = b" up 01:02:03 | heap 2 KiB free | 10 frames free | CAPS num | tty3";
+    assert_eq!(&text[..expected.len()], &expected[..]);
+    assert!(text[expected.len()..].iter().all(|byte| *byte == b' '));
+}
@@ -0,0 +1,75 @@
+use core::char::REPLACEMENT_CHARACTER;
+
+// incremental decoder for byte streams where a character may be split across writes
+#[derive(Default)]
+pub struct Utf8Decoder {
+    code_point: u32,
+    remaining: u8,
+    minimum: u32,
+}
+
+impl Utf8Decoder {
+
+    pub const fn new() -> Utf8Decoder {
+        return Utf8Decoder {
+            code_point: 0,
+            remaining: 0,
+            minimum: 0
+        };
+    }
+
+    pub fn push<F: FnMut(char)>(&mut self, byte: u8, mut emit: F) {
+        if self.remaining > 0 {
+            if byte & 0xc0 == 0x80 {
+                self.code_point = self.code_point << 6 | (byte & 0x3f) as u32;
+                self.remaining = self.remaining - 1;
+                if self.remaining == 0 {
+                    let c = match self.code_point >= self.minimum {
+                        true => core::char::from_u32(self.code_point),
+                        false => None // overlong encoding
+                    };
+                    emit(c.unwrap_or(REPLACEMENT_CHARACTER));
+                }
+                return;
+            }
+            // truncated sequence, the new byte starts over
+            self.remaining = 0;
+            emit(REPLACEMENT_CHARACTER);
+        }
+
+        let (code_point, remaining, minimum) = match byte {
+            0x00..=0x7f => return emit(byte as char),
+            0xc2..=0xdf => (byte & 0x1f, 1, 0x80),
+            0xe0..=0xef => (byte & 0x0f, 2, 0x800),
+            0xf0..=0xf4 => (byte & 0x07, 3, 0x10000),
+            _ => return emit(REPLACEMENT_CHARACTER)
+        };
+        self.code_point = code_point as u32;
+        self.remaining = remaining;
+        self.minimum = minimum;
+    }
+}
+
+#[test_case]
+fn test_decode_split_sequence() {
+    let mut decoder = Utf8Decoder::new();
+    let mut decoded = None;
+    for byte in "é".bytes() {
+        decoder.push(byte, |c| decoded = Some(c));
+    }
+    assert_eq!(decoded, Some('é'));
+}
+
+#[test_case]
+fn test_decode_invalid_bytes() {
+    let mut decoder = Utf8Decoder::new();
+    let mut decoded = ['\0'; 4];
+    let mut count = 0;
+    for byte in [0xe2, 0x96, b'a', 0xff].iter() {
+        decoder.push(*byte, |c| {
+            decoded[count] = c;
+            count = count + 1;
+        });
+    }
+    assert_eq!(&decoded[..count], &[REPLACEMENT_CHARACTER, 'a', REPLACEMENT_CHARACTER]);
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(ros::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+use bootloader::{BootInfo, entry_point};
+use ros::framebuffer::{self, CONSOLE};
+use ros::framebuffer::font::{GLYPH_HEIGHT, GLYPH_WIDTH};
+use ros::println;
+
+entry_point!(test_kernel_main);
+
+fn test_kernel_main(boot_info: &'static BootInfo) -> ! {
+    ros::init();
+    ros::memory::init(boot_info);
+    framebuffer::init(1024, 768).expect("could not set the graphics mode");
+    test_main();
+    ros::halt();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    ros::test_panic_handler(info);
+}
+
+#[test_case]
+fn console_has_more_cells() {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let console = CONSOLE.lock();
+        let console = console.as_ref().unwrap();
+        assert_eq!(console.columns(), 1024 / GLYPH_WIDTH);
+        assert_eq!(console.rows(), 768 / GLYPH_HEIGHT);
+    });
+}
+
+#[test_case]
+fn println_draws_pixels() {
+    println!("\x1b[2J\x1b[97m#");
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut console = CONSOLE.lock();
+        let framebuffer = console.as_mut().unwrap().framebuffer();
+        let lit = (0..GLYPH_HEIGHT)
+            .flat_map(|y| (0..GLYPH_WIDTH).map(move |x| (x, y)))
+            .filter(|(x, y)| framebuffer.pixel(*x, *y) == 0xffffff)
+            .count();
+        assert!(lit > 0);
+    });
+}
+
+#[test_case]
+fn println_many_lines() {
+    for i in 0..200 {
+        println!("framebuffer line {}", i);
+    }
+}
@@ -0,0 +1,103 @@
+#![no_std]
+#![no_main]
+
+use bootloader::{BootInfo, entry_point};
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::VirtAddr;
+use x86_64::structures::paging::PageTableFlags;
+use ros::smp::{self, call, percpu};
+use ros::{serial_print, serial_println, exit_qemu, QemuExitCode};
+
+// matches the -smp in the bootimage test-args
+const EXPECTED_CPUS: usize = 4;
+
+const TEST_PAGE: u64 = 0x_2000_0000;
+
+static SEEN: [AtomicU64; EXPECTED_CPUS] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    ros::init();
+    ros::memory::init(boot_info);
+
+    serial_print!("smp::all_cpus_check_in... ");
+    let madt = ros::acpi::madt().expect("no madt");
+    let enabled = madt.processors.iter().filter(|processor| processor.enabled).count();
+    assert_eq!(enabled, EXPECTED_CPUS);
+    let online = smp::init().expect("smp initialization failed");
+    assert_eq!(online, EXPECTED_CPUS);
+    assert_eq!(percpu::cpu_id(), 0);
+    serial_println!("[ok]");
+
+    serial_print!("smp::call_on_every_cpu... ");
+    call::call_on_all(&|| {
+        SEEN[percpu::cpu_id()].store(percpu::this_cpu().cpu as u64 + 1, Ordering::Relaxed);
+    });
+    for (cpu, seen) in SEEN.iter().enumerate() {
+        assert_eq!(seen.load(Ordering::Relaxed), cpu as u64 + 1);
+    }
+    serial_println!("[ok]");
+
+    serial_print!("smp::no_stale_translation_after_unmap... ");
+    let page = VirtAddr::new(TEST_PAGE);
+    ros::memory::map_user_region(page, 4096).expect("mapping the test page failed");
+    unsafe { write(0xaaaa) };
+    // every other cpu caches the translation to the first frame
+    assert_eq!(read_on_others(), 0xaaaa);
+    ros::memory::unmap_region(page, 4096).expect("unmapping the test page failed");
+    // a fresh frame behind the same address, the old one keeps its contents
+    ros::memory::map_user_region(page, 4096).expect("remapping the test page failed");
+    unsafe { write(0xbbbb) };
+    assert_eq!(read_on_others(), 0xbbbb);
+    serial_println!("[ok]");
+
+    serial_print!("smp::protect_flushes_other_cpus... ");
+    let before = flushes_on_others();
+    let read_only = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+    ros::memory::protect_region(page, 4096, read_only).expect("protecting the test page failed");
+    let after = flushes_on_others();
+    for (after, before) in after.iter().zip(before.iter()).skip(1) {
+        assert_eq!(*after, before + 1);
+    }
+    serial_println!("[ok]");
+
+    exit_qemu(QemuExitCode::Success);
+    ros::halt();
+}
+
+unsafe fn write(value: u64) {
+    core::ptr::write_volatile(TEST_PAGE as *mut u64, value);
+}
+
+// the value each of the other cpus reads from the test page, they all have to agree
+fn read_on_others() -> u64 {
+    call::call_on_others(&|| {
+        let value = unsafe { core::ptr::read_volatile(TEST_PAGE as *const u64) };
+        SEEN[percpu::cpu_id()].store(value, Ordering::Relaxed);
+    });
+    let value = SEEN[1].load(Ordering::Relaxed);
+    for seen in SEEN.iter().skip(1) {
+        assert_eq!(seen.load(Ordering::Relaxed), value);
+    }
+    return value;
+}
+
+// a write through a stale read-only translation can't be caught without a fault
+// handler, so count the shootdowns each cpu handled instead
+fn flushes_on_others() -> [u64; EXPECTED_CPUS] {
+    call::call_on_others(&|| {
+        SEEN[percpu::cpu_id()].store(ros::smp::tlb::flushes(), Ordering::Relaxed);
+    });
+    let mut flushes = [0; EXPECTED_CPUS];
+    for (cpu, seen) in SEEN.iter().enumerate().skip(1) {
+        flushes[cpu] = seen.load(Ordering::Relaxed);
+    }
+    return flushes;
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    ros::test_panic_handler(info);
+}
@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+use bootloader::{BootInfo, entry_point};
+use core::panic::PanicInfo;
+use ros::syscall::{self, Arguments, Errno};
+use ros::{serial_print, serial_println, exit_qemu, QemuExitCode};
+
+const SYS_ARGUMENTS: usize = 10;
+const SYS_CHECK: usize = 11;
+const SYS_UNKNOWN: u32 = 63;
+
+// SYS_ARGUMENTS(1, 2, 3, 4, 5, 6), keep its result in rdi across an unknown
+// system call, then SYS_CHECK(first result, second result)
+const PROGRAM: [u8; 73] = [
+    0x48, 0xc7, 0xc0, SYS_ARGUMENTS as u8, 0, 0, 0, // mov rax, SYS_ARGUMENTS
+    0x48, 0xc7, 0xc7, 1, 0, 0, 0,                   // mov rdi, 1
+    0x48, 0xc7, 0xc6, 2, 0, 0, 0,                   // mov rsi, 2
+    0x48, 0xc7, 0xc2, 3, 0, 0, 0,                   // mov rdx, 3
+    0x49, 0xc7, 0xc2, 4, 0, 0, 0,                   // mov r10, 4
+    0x49, 0xc7, 0xc0, 5, 0, 0, 0,                   // mov r8, 5
+    0x49, 0xc7, 0xc1, 6, 0, 0, 0,                   // mov r9, 6
+    0x0f, 0x05,                                     // syscall
+    0x48, 0x89, 0xc7,                               // mov rdi, rax
+    0xb8, SYS_UNKNOWN as u8, 0, 0, 0,               // mov eax, SYS_UNKNOWN
+    0x0f, 0x05,                                     // syscall
+    0x48, 0x89, 0xc6,                               // mov rsi, rax
+    0xb8, SYS_CHECK as u8, 0, 0, 0,                 // mov eax, SYS_CHECK
+    0x0f, 0x05,                                     // syscall
+    0xeb, 0xfe,                                     // spin in case it returns
+];
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("syscall::syscall_from_ring3... ");
+
+    ros::gdt::init();
+    ros::syscall::init();
+    // faults from ring 3 have to come in through the kernel's stubs to find the per-cpu block
+    ros::interrupts::init_idt();
+    ros::interrupts::mask_pics();
+    ros::memory::init(boot_info);
+
+    syscall::register(SYS_ARGUMENTS, sys_arguments).unwrap();
+    syscall::register(SYS_CHECK, sys_check).unwrap();
+
+    unsafe { ros::usermode::run(&PROGRAM) }
+}
+
+fn sys_arguments(arguments: &Arguments) -> Result<u64, Errno> {
+    assert_eq!(arguments.0, [1, 2, 3, 4, 5, 6]);
+    return Ok(42);
+}
+
+fn sys_check(arguments: &Arguments) -> Result<u64, Errno> {
+    assert_eq!(arguments.0[0], 42);
+    assert_eq!(arguments.0[1] as i64, -(Errno::ENOSYS as i64));
+    serial_println!("[ok]");
+    exit_qemu(QemuExitCode::Success);
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    ros::test_panic_handler(info);
+}
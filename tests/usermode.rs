@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+use bootloader::{BootInfo, entry_point};
+use core::panic::PanicInfo;
+use ros::interrupts::trap::BREAKPOINT_VECTOR;
+use ros::syscall::{self, Arguments, Errno};
+use ros::{serial_print, serial_println, exit_qemu, QemuExitCode};
+
+const SYS_CHECK: usize = 11;
+
+// int3 through the kernel's breakpoint gate, then SYS_CHECK to look at what it recorded
+const PROGRAM: [u8; 10] = [
+    0xcc,                           // int3
+    0xb8, SYS_CHECK as u8, 0, 0, 0, // mov eax, SYS_CHECK
+    0x0f, 0x05,                     // syscall
+    0xeb, 0xfe,                     // spin in case it returns
+];
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("usermode::int3_from_ring3... ");
+
+    ros::gdt::init();
+    ros::syscall::init();
+    ros::interrupts::init_idt();
+    ros::interrupts::mask_pics();
+    ros::memory::init(boot_info);
+
+    syscall::register(SYS_CHECK, sys_check).unwrap();
+
+    unsafe { ros::usermode::run(&PROGRAM) }
+}
+
+fn sys_check(_arguments: &Arguments) -> Result<u64, Errno> {
+    let frame = ros::interrupts::last_breakpoint().expect("breakpoint never reached the kernel handler");
+    assert_eq!(frame.vector, BREAKPOINT_VECTOR);
+    assert_eq!(frame.cs & 3, 3, "breakpoint did not come from ring 3");
+    assert_eq!(frame.rip, ros::usermode::PROGRAM_START + 1, "unexpected return address");
+    serial_println!("[ok]");
+    exit_qemu(QemuExitCode::Success);
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    ros::test_panic_handler(info);
+}